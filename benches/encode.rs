@@ -0,0 +1,221 @@
+//! Encoder throughput on the bundled corpora.
+//!
+//! Each benchmark encodes a whole word list with one reused encoder and reports
+//! words per second. Run with `cargo bench`; compare against a saved baseline
+//! with `cargo bench -- --save-baseline main` and `--baseline main`.
+use std::hint::black_box;
+use std::time::Duration;
+
+use criterion::measurement::WallTime;
+use criterion::{BenchmarkGroup, Criterion, Throughput, criterion_group, criterion_main};
+use metaphone3::cache::CachedMetaphone3;
+use metaphone3::{Metaphone3, Metaphone3Config, Mode};
+
+/// Names with the non-ASCII letters the encoder handles specially (`Ç`, `Ñ`,
+/// `Ð`, `Þ`) plus common accented vowels it treats as non-letters. `ß` is
+/// uppercased to `SS` before encoding, so its names measure that expansion
+/// and then plain ASCII rules, not a non-ASCII rule.
+const NON_ASCII: &[&str] = &[
+    "Muñoz",
+    "Núñez",
+    "Ibáñez",
+    "Peña",
+    "Françoise",
+    "Gonçalves",
+    "Façade",
+    "Groß",
+    "Strauß",
+    "Weißmann",
+    "Þórsson",
+    "Guðmundsdóttir",
+    "Björk",
+    "Müller",
+    "Schröder",
+    "Jürgen",
+    "Zoë",
+    "Renée",
+    "José",
+    "Álvarez",
+    "Ołdakowski",
+    "Dvořák",
+    "Łukasz",
+    "Øster",
+    "Ångström",
+];
+
+/// Number of `count_1w.txt` entries to encode; the full list is 333k words.
+const DICTIONARY_WORDS: usize = 50_000;
+
+fn load(path: &str) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("{path}: {err} (run from the crate root)"))
+        .lines()
+        .filter_map(|l| l.split(',').next())
+        .filter(|w| !w.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn bench_words(group: &mut BenchmarkGroup<'_, WallTime>, name: &str, mode: Mode, words: &[String]) {
+    let mut encoder = Metaphone3::new().with_mode(mode);
+    group.throughput(Throughput::Elements(words.len() as u64));
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            for word in words {
+                black_box(encoder.encode(black_box(word)));
+            }
+        });
+    });
+}
+
+fn containing(words: &[String], needles: &[&str]) -> Vec<String> {
+    words
+        .iter()
+        .filter(|w| {
+            let upper = w.to_uppercase();
+            needles.iter().any(|n| upper.contains(n))
+        })
+        .cloned()
+        .collect()
+}
+
+fn corpora(c: &mut Criterion) {
+    let surnames = load("testdata/surnames-us.txt");
+    let firstnames = load("testdata/firstnames-us.txt");
+    let mut dictionary = load("testdata/count_1w.txt");
+    dictionary.truncate(DICTIONARY_WORDS);
+
+    let mut group = c.benchmark_group("corpus");
+    for mode in Mode::ALL {
+        bench_words(&mut group, &format!("surnames/{mode}"), mode, &surnames);
+    }
+    bench_words(&mut group, "firstnames/Enc", Mode::Enc, &firstnames);
+    bench_words(&mut group, "count_1w/Enc", Mode::Enc, &dictionary);
+    group.finish();
+}
+
+fn word_shapes(c: &mut Criterion) {
+    let surnames = load("testdata/surnames-us.txt");
+    let short: Vec<String> = surnames.iter().filter(|w| w.len() <= 4).cloned().collect();
+    let long: Vec<String> = surnames.iter().filter(|w| w.len() >= 10).cloned().collect();
+    let non_ascii: Vec<String> = NON_ASCII.iter().map(|&w| w.to_owned()).collect();
+
+    let mut group = c.benchmark_group("shape");
+    bench_words(&mut group, "short", Mode::Enc, &short);
+    bench_words(&mut group, "long", Mode::Enc, &long);
+    bench_words(&mut group, "non_ascii", Mode::Enc, &non_ascii);
+    group.finish();
+}
+
+/// Surnames that route through the larger per-letter rule families, where most
+/// of the `string_at` lookups happen.
+fn rule_families(c: &mut Criterion) {
+    let surnames = load("testdata/surnames-us.txt");
+    let families: &[(&str, &[&str])] = &[
+        ("ch", &["CH"]),
+        ("gh", &["GH"]),
+        ("g_front_vowel", &["GE", "GI", "GY"]),
+        ("sch_sh", &["SCH", "SH"]),
+        ("th", &["TH"]),
+        ("w", &["W"]),
+        ("j", &["J"]),
+        ("l", &["LL", "LE"]),
+    ];
+
+    let mut group = c.benchmark_group("rules");
+    for &(name, needles) in families {
+        let words = containing(&surnames, needles);
+        bench_words(&mut group, name, Mode::Enc, &words);
+        bench_words(&mut group, &format!("{name}/EncV"), Mode::EncV, &words);
+    }
+    group.finish();
+}
+
+/// The `&self` path of a shared configuration against the reused `&mut`
+/// encoder, on the same words.
+fn shared(c: &mut Criterion) {
+    let firstnames = load("testdata/firstnames-us.txt");
+    let config = Metaphone3Config::new();
+
+    let mut group = c.benchmark_group("shared");
+    bench_words(&mut group, "mut", Mode::Enc, &firstnames);
+    group.throughput(Throughput::Elements(firstnames.len() as u64));
+    group.bench_function("config", |b| {
+        b.iter(|| {
+            for word in &firstnames {
+                black_box(config.encode(black_box(word)));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Keys for all four modes: one encoder per mode against `encode_all_modes`.
+fn all_modes(c: &mut Criterion) {
+    let surnames = load("testdata/surnames-us.txt");
+
+    let mut group = c.benchmark_group("all_modes");
+    group.throughput(Throughput::Elements(surnames.len() as u64));
+    let mut encoders = Mode::ALL.map(|mode| Metaphone3::new().with_mode(mode));
+    group.bench_function("separate", |b| {
+        b.iter(|| {
+            for word in &surnames {
+                for encoder in &mut encoders {
+                    black_box(encoder.encode(black_box(word)));
+                }
+            }
+        });
+    });
+    let mut encoder = Metaphone3::new();
+    group.bench_function("combined", |b| {
+        b.iter(|| {
+            for word in &surnames {
+                black_box(encoder.encode_all_modes(black_box(word)));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// A column of repeated names, the 1000 most common surnames cycled, with and
+/// without a cache that holds them all.
+fn cached(c: &mut Criterion) {
+    let surnames = load("testdata/surnames-us.txt");
+    let column: Vec<String> = surnames
+        .iter()
+        .take(1000)
+        .cycle()
+        .take(50_000)
+        .cloned()
+        .collect();
+
+    let mut group = c.benchmark_group("cached");
+    bench_words(&mut group, "uncached", Mode::Enc, &column);
+    let mut encoder = CachedMetaphone3::new(Metaphone3::new(), 1000);
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for word in &column {
+                black_box(encoder.encode(black_box(word)));
+            }
+        });
+    });
+    group.finish();
+}
+
+fn config() -> Criterion {
+    // Longer measurements and a tighter noise threshold than the defaults keep
+    // run-to-run variance below the few-percent regressions we care about.
+    Criterion::default()
+        .warm_up_time(Duration::from_secs(2))
+        .measurement_time(Duration::from_secs(5))
+        .sample_size(50)
+        .noise_threshold(0.02)
+        .significance_level(0.01)
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = corpora, word_shapes, rule_families, shared, all_modes, cached
+}
+criterion_main!(benches);
@@ -0,0 +1,160 @@
+//! [Metaphone 3](metaphone3) keys as a SQL function for
+//! [`DataFusion`](datafusion_expr).
+//!
+//! [`Metaphone3Udf`] is a scalar function `metaphone3(name)` that takes a
+//! `Utf8`, `LargeUtf8` or `Utf8View` column and returns a struct of `primary`
+//! and `secondary` keys. Null names give a null struct. Register it with
+//! [`metaphone3_udf`]:
+//!
+//! ```
+//! use datafusion::prelude::SessionContext;
+//! use metaphone3_datafusion::metaphone3_udf;
+//!
+//! # async fn keys(ctx: SessionContext) -> datafusion::error::Result<()> {
+//! ctx.register_udf(metaphone3_udf());
+//! let keys = ctx
+//!     .sql("SELECT name, metaphone3(name)['primary'] AS key FROM people")
+//!     .await?
+//!     .collect()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each batch is encoded with one [`Metaphone3`] made from the function's
+//! [`Metaphone3Config`], through [`Metaphone3::encode_array`].
+
+#![warn(clippy::pedantic)]
+
+use std::any::Any;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use arrow_array::{Array, ArrayRef, StructArray};
+use arrow_schema::{DataType, Field, Fields};
+use datafusion_common::{DataFusionError, Result};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+};
+use metaphone3::{Metaphone3, Metaphone3Config};
+
+/// The `metaphone3(name)` scalar function. See the
+/// [crate documentation](crate).
+pub struct Metaphone3Udf {
+    name: String,
+    config: Metaphone3Config,
+    signature: Signature,
+}
+
+impl Metaphone3Udf {
+    /// A `metaphone3` function that encodes with `config`.
+    #[must_use]
+    pub fn new(config: Metaphone3Config) -> Self {
+        Metaphone3Udf {
+            name: "metaphone3".to_owned(),
+            config,
+            signature: Signature::uniform(
+                1,
+                vec![DataType::Utf8, DataType::LargeUtf8, DataType::Utf8View],
+                Volatility::Immutable,
+            ),
+        }
+    }
+
+    /// Registers the function under `name` instead of `metaphone3`, so
+    /// functions of several configurations can be used side by side.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The configuration the function encodes with.
+    #[must_use]
+    pub fn config(&self) -> &Metaphone3Config {
+        &self.config
+    }
+}
+
+impl Default for Metaphone3Udf {
+    fn default() -> Self {
+        Metaphone3Udf::new(Metaphone3Config::new())
+    }
+}
+
+/// The default `metaphone3` function, ready to register.
+#[must_use]
+pub fn metaphone3_udf() -> ScalarUDF {
+    ScalarUDF::new_from_impl(Metaphone3Udf::default())
+}
+
+/// The fields of the returned struct.
+fn key_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("primary", DataType::Utf8, true),
+        Field::new("secondary", DataType::Utf8, true),
+    ])
+}
+
+impl ScalarUDFImpl for Metaphone3Udf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Struct(key_fields()))
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> Result<ColumnarValue> {
+        let [name] = args.args.as_slice() else {
+            return Err(DataFusionError::Plan(format!(
+                "{} takes 1 argument, got {}",
+                self.name,
+                args.args.len()
+            )));
+        };
+        let names = name.to_array(args.number_rows)?;
+        let mut encoder = Metaphone3::from(self.config.clone());
+        let (primary, secondary) = encoder.encode_array(&names)?;
+        let keys = StructArray::try_new(
+            key_fields(),
+            vec![Arc::new(primary) as ArrayRef, Arc::new(secondary)],
+            names.nulls().cloned(),
+        )?;
+        Ok(ColumnarValue::Array(Arc::new(keys)))
+    }
+}
+
+impl std::fmt::Debug for Metaphone3Udf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metaphone3Udf")
+            .field("name", &self.name)
+            .field("fingerprint", &self.config.fingerprint())
+            .finish_non_exhaustive()
+    }
+}
+
+// DataFusion compares functions to deduplicate expressions; two functions are
+// the same if they have the same name and configuration fingerprint.
+impl PartialEq for Metaphone3Udf {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.config.fingerprint() == other.config.fingerprint()
+    }
+}
+
+impl Eq for Metaphone3Udf {}
+
+impl Hash for Metaphone3Udf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.config.fingerprint().hash(state);
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use datafusion::arrow::array::{Array, LargeStringArray, RecordBatch, StringArray, StructArray};
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::error::Result;
+use datafusion::prelude::SessionContext;
+use metaphone3::{Metaphone3, Metaphone3Config, Mode};
+use metaphone3_datafusion::{Metaphone3Udf, metaphone3_udf};
+
+const NAMES: [Option<&str>; 5] = [
+    Some("Smith"),
+    None,
+    Some(""),
+    Some("Schmidt"),
+    Some("Jones"),
+];
+
+fn context() -> Result<SessionContext> {
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, true),
+        Field::new("large", DataType::LargeUtf8, true),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from(NAMES.to_vec())),
+            Arc::new(LargeStringArray::from(NAMES.to_vec())),
+        ],
+    )?;
+    let ctx = SessionContext::new();
+    ctx.register_batch("t", batch)?;
+    ctx.register_udf(metaphone3_udf());
+    Ok(ctx)
+}
+
+async fn query(ctx: &SessionContext, sql: &str) -> Result<RecordBatch> {
+    let batches = ctx.sql(sql).await?.collect().await?;
+    Ok(concat_batches(&batches[0].schema(), &batches)?)
+}
+
+fn strings(array: &dyn Array) -> Vec<Option<String>> {
+    let array = array.as_any().downcast_ref::<StringArray>().unwrap();
+    array.iter().map(|v| v.map(str::to_owned)).collect()
+}
+
+/// The primary or secondary keys of `NAMES`, from `Metaphone3::encode`.
+fn expected(primary: bool) -> Vec<Option<String>> {
+    let mut encoder = Metaphone3::new();
+    NAMES
+        .iter()
+        .map(|name| {
+            let keys = encoder.encode((*name)?);
+            Some(if primary { keys.0 } else { keys.1 }.to_string())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_select_metaphone3() -> Result<()> {
+    let ctx = context()?;
+    let batch = query(&ctx, "SELECT metaphone3(name) FROM t").await?;
+    let keys = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .unwrap();
+    assert_eq!(keys.len(), NAMES.len());
+    assert!(keys.is_null(1) && !keys.is_null(2));
+    assert_eq!(
+        strings(keys.column_by_name("primary").unwrap()),
+        expected(true)
+    );
+    assert_eq!(
+        strings(keys.column_by_name("secondary").unwrap()),
+        expected(false)
+    );
+
+    // Field access, and the other string types.
+    let batch = query(
+        &ctx,
+        "SELECT metaphone3(large)['primary'], \
+         metaphone3(arrow_cast(name, 'Utf8View'))['secondary'] FROM t",
+    )
+    .await?;
+    assert_eq!(strings(batch.column(0)), expected(true));
+    assert_eq!(strings(batch.column(1)), expected(false));
+
+    let batch = query(&ctx, "SELECT metaphone3('Smith')['secondary']").await?;
+    assert_eq!(strings(batch.column(0)), [Some("XMT".to_owned())]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_configured_udf() -> Result<()> {
+    let ctx = context()?;
+    let vowels = Metaphone3Udf::new(Metaphone3Config::new().with_mode(Mode::EncV))
+        .with_name("metaphone3_vowels");
+    ctx.register_udf(vowels.into());
+    let batch = query(
+        &ctx,
+        "SELECT metaphone3_vowels(name)['primary'] FROM t WHERE name = 'Jones'",
+    )
+    .await?;
+    assert_eq!(strings(batch.column(0)), [Some("JANS".to_owned())]);
+
+    assert!(
+        ctx.sql("SELECT metaphone3('Smith', 'Jones')")
+            .await
+            .is_err()
+    );
+    Ok(())
+}
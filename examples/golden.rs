@@ -0,0 +1,50 @@
+//! Regenerates or checks the golden corpora in `testdata/`.
+//!
+//! ```sh
+//! # Show every key the current encoder changes, exit 1 if any did
+//! cargo run --release --example golden -- diff testdata/surnames-us-metaphone3.test
+//!
+//! # Rewrite a corpus from the current encoder; the input may be a word list
+//! # (one word per line) or an existing corpus (only the words are read)
+//! cargo run --release --example golden -- regen testdata/surnames-us.txt testdata/surnames-us-metaphone3.test
+//! ```
+use metaphone3::golden::{self, GoldenEncoder};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut encoder = GoldenEncoder::new();
+
+    match args.as_slice() {
+        [cmd, corpus] if cmd == "diff" => {
+            let records = golden::read_corpus(BufReader::new(File::open(corpus)?))?;
+            let diff = encoder.check(&records);
+            print!("{diff}");
+            Ok(if diff.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        [cmd, input, output] if cmd == "regen" => {
+            let text = std::fs::read_to_string(input)?;
+            let records = golden::read_corpus(text.as_bytes()).unwrap_or_default();
+            let words: Vec<&str> = if records.is_empty() {
+                text.lines().filter(|w| !w.is_empty()).collect()
+            } else {
+                records.iter().map(|r| r.word.as_str()).collect()
+            };
+            let mut out = BufWriter::new(File::create(output)?);
+            encoder.write_corpus(&mut out, words)?;
+            out.flush()?;
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => {
+            eprintln!("usage: golden diff <corpus.test>");
+            eprintln!("       golden regen <words.txt> <corpus.test>");
+            Ok(ExitCode::from(2))
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Encodes arbitrary Unicode in all four modes and checks the output invariants.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use metaphone3::{METAPH_MAX_LENGTH, Metaphone3, Mode};
+
+fuzz_target!(|word: &str| {
+    for mode in Mode::ALL {
+        let mut encoder = Metaphone3::new().with_mode(mode);
+        let (primary, secondary) = encoder.encode(word);
+
+        assert!(
+            primary.chars().count() <= METAPH_MAX_LENGTH,
+            "{mode}: {word:?}"
+        );
+        assert!(
+            secondary.chars().count() <= METAPH_MAX_LENGTH,
+            "{mode}: {word:?}"
+        );
+        assert!(
+            secondary.is_empty() || secondary != primary,
+            "{mode}: secondary equals primary for {word:?}"
+        );
+
+        // A reused encoder must not carry state over from the previous word.
+        encoder.encode("Gallagher");
+        assert_eq!(
+            encoder.encode(word),
+            (primary, secondary),
+            "{mode}: {word:?}"
+        );
+    }
+});
@@ -0,0 +1,47 @@
+//! Checks the encoder against the reference outputs in `testdata/`.
+//!
+//! The fuzzer picks a record from the golden corpora and flips the case of its
+//! letters; the keys must still match what the reference implementation
+//! recorded for every mode.
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use metaphone3::golden::{GoldenEncoder, GoldenRecord};
+
+static RECORDS: LazyLock<Vec<GoldenRecord>> = LazyLock::new(|| {
+    [
+        include_str!("../../testdata/firstnames-us-metaphone3.test"),
+        include_str!("../../testdata/surnames-us-metaphone3.test"),
+    ]
+    .iter()
+    .flat_map(|corpus| corpus.lines())
+    .map(|line| GoldenRecord::parse(line).expect("malformed golden record"))
+    .collect()
+});
+
+fuzz_target!(|input: (u32, u64)| {
+    let (pick, case_mask) = input;
+    let expected = &RECORDS[pick as usize % RECORDS.len()];
+
+    let word: String = expected
+        .word
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if case_mask >> (i % 64) & 1 == 1 {
+                c.to_ascii_lowercase()
+            } else {
+                c.to_ascii_uppercase()
+            }
+        })
+        .collect();
+
+    let actual = GoldenEncoder::new().record(&word);
+    assert_eq!(
+        actual.keys, expected.keys,
+        "{word:?} (from {:?})",
+        expected.word
+    );
+});
@@ -0,0 +1,138 @@
+//! Compile-time [Metaphone 3](metaphone3) keys.
+//!
+//! [`metaphone3!`] encodes a string literal while the crate is compiled and
+//! expands to the keys as string literals, so keys of known names cost nothing
+//! at startup and can be used in `const` tables and `match` patterns. The
+//! encoding runs the same [`metaphone3::Metaphone3`] rules as at run time.
+//!
+//! ```
+//! use metaphone3_macros::metaphone3;
+//!
+//! const SMITH: (&str, &str) = metaphone3!("Smith");
+//! assert_eq!(SMITH, ("SM0", "XMT"));
+//!
+//! let mut encoder = metaphone3::Metaphone3::new();
+//! let kind = match encoder.encode("Smyth").0.as_str() {
+//!     metaphone3!("Smith", key = primary) => "smith",
+//!     metaphone3!("Jones", key = primary) => "jones",
+//!     _ => "other",
+//! };
+//! assert_eq!(kind, "smith");
+//! ```
+
+#![warn(clippy::pedantic)]
+
+use metaphone3::Metaphone3;
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitBool, LitStr, Token, parse_macro_input};
+
+/// Encodes a string literal at compile time.
+///
+/// `metaphone3!("word")` expands to the `(primary, secondary)` keys as a tuple
+/// of `&'static str` literals. Options follow the word, separated by commas:
+///
+/// - `vowels = true` and `exact = true` select the
+///   [vowel](metaphone3::Metaphone3::with_encode_vowels) and
+///   [exact](metaphone3::Metaphone3::with_encode_exact) modes; both are off by
+///   default.
+/// - `key = primary` or `key = secondary` expands to that key alone, as a
+///   single literal, which can be used as a `match` pattern.
+///
+/// ```
+/// use metaphone3_macros::metaphone3;
+///
+/// assert_eq!(metaphone3!("Smith", vowels = true), ("SMA0", "XMAT"));
+/// assert_eq!(metaphone3!("Schmidt", key = primary), "XMT");
+/// ```
+///
+/// Exception dictionaries and custom rules are run-time values and cannot be
+/// used here; names they would change have to be encoded at run time.
+#[proc_macro]
+pub fn metaphone3(input: TokenStream) -> TokenStream {
+    let Input {
+        word,
+        vowels,
+        exact,
+        key,
+    } = parse_macro_input!(input as Input);
+    let (primary, secondary) = Metaphone3::new()
+        .with_encode_vowels(vowels)
+        .with_encode_exact(exact)
+        .encode(&word.value());
+    let literal = |key: &str| LitStr::new(key, word.span());
+    let (primary, secondary) = (literal(&primary), literal(&secondary));
+    match key {
+        None => quote!((#primary, #secondary)),
+        Some(Key::Primary) => quote!(#primary),
+        Some(Key::Secondary) => quote!(#secondary),
+    }
+    .into()
+}
+
+/// Which key `key = ...` selects.
+enum Key {
+    Primary,
+    Secondary,
+}
+
+/// The parsed arguments of [`metaphone3!`].
+struct Input {
+    word: LitStr,
+    vowels: bool,
+    exact: bool,
+    key: Option<Key>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut parsed = Input {
+            word: input.parse()?,
+            vowels: false,
+            exact: false,
+            key: None,
+        };
+        let mut seen: Vec<String> = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let option = name.to_string();
+            if seen.contains(&option) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("duplicate option `{option}`"),
+                ));
+            }
+            match option.as_str() {
+                "vowels" => parsed.vowels = input.parse::<LitBool>()?.value,
+                "exact" => parsed.exact = input.parse::<LitBool>()?.value,
+                "key" => {
+                    let value: Ident = input.parse()?;
+                    parsed.key = Some(match value.to_string().as_str() {
+                        "primary" => Key::Primary,
+                        "secondary" => Key::Secondary,
+                        _ => {
+                            return Err(syn::Error::new(
+                                value.span(),
+                                "expected `primary` or `secondary`",
+                            ));
+                        }
+                    });
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("unknown option `{option}`, expected `vowels`, `exact` or `key`"),
+                    ));
+                }
+            }
+            seen.push(option);
+        }
+        Ok(parsed)
+    }
+}
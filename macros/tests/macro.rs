@@ -0,0 +1,81 @@
+use metaphone3::Metaphone3;
+use metaphone3_macros::metaphone3;
+
+fn encode(word: &str, vowels: bool, exact: bool) -> (String, String) {
+    let (primary, secondary) = Metaphone3::new()
+        .with_encode_vowels(vowels)
+        .with_encode_exact(exact)
+        .encode(word);
+    (primary.to_string(), secondary.to_string())
+}
+
+fn owned((primary, secondary): (&str, &str)) -> (String, String) {
+    (primary.to_string(), secondary.to_string())
+}
+
+#[test]
+fn test_matches_runtime_encoder() {
+    assert_eq!(
+        owned(metaphone3!("Schmidt")),
+        encode("Schmidt", false, false)
+    );
+    assert_eq!(
+        owned(metaphone3!("Schmidt", vowels = true)),
+        encode("Schmidt", true, false)
+    );
+    assert_eq!(
+        owned(metaphone3!("Schmidt", exact = true)),
+        encode("Schmidt", false, true)
+    );
+    assert_eq!(
+        owned(metaphone3!("Schmidt", exact = true, vowels = true,)),
+        encode("Schmidt", true, true)
+    );
+    assert_eq!(owned(metaphone3!("Jones")), encode("Jones", false, false));
+    assert_eq!(owned(metaphone3!("")), encode("", false, false));
+    assert_eq!(
+        owned(metaphone3!("Müller", vowels = false)),
+        encode("Müller", false, false)
+    );
+}
+
+#[test]
+fn test_single_key() {
+    assert_eq!(metaphone3!("Smith", key = primary), "SM0");
+    assert_eq!(metaphone3!("Smith", key = secondary), "XMT");
+    assert_eq!(metaphone3!("Smith", vowels = true, key = primary), "SMA0");
+}
+
+const TABLE: &[(&str, (&str, &str))] = &[
+    ("Smith", metaphone3!("Smith")),
+    ("Schmidt", metaphone3!("Schmidt")),
+    ("Jones", metaphone3!("Jones")),
+];
+
+#[test]
+fn test_const_table() {
+    let mut encoder = Metaphone3::new();
+    for &(name, keys) in TABLE {
+        let (primary, secondary) = encoder.encode(name);
+        assert_eq!(keys, (primary.as_str(), secondary.as_str()), "{name}");
+    }
+}
+
+#[test]
+fn test_match_patterns() {
+    let mut encoder = Metaphone3::new();
+    let classify = |encoder: &mut Metaphone3, name: &str| match encoder.encode(name) {
+        (primary, _) if primary == metaphone3!("Smith", key = primary) => "smith",
+        (primary, _) => match primary.as_str() {
+            metaphone3!("Jones", key = primary) => "jones",
+            _ => "other",
+        },
+    };
+    assert_eq!(classify(&mut encoder, "Smyth"), "smith");
+    assert_eq!(classify(&mut encoder, "Johns"), "jones");
+    assert_eq!(classify(&mut encoder, "Brown"), "other");
+    match ("SM0", "XMT") {
+        metaphone3!("Smith") => {}
+        _ => panic!("tuple pattern did not match"),
+    }
+}
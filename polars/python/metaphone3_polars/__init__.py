@@ -0,0 +1,35 @@
+"""Metaphone3 keys as a Polars expression."""
+
+from __future__ import annotations
+
+from pathlib import Path
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from metaphone3_polars._internal import __version__
+
+if TYPE_CHECKING:
+    import polars as pl
+    from polars._typing import IntoExpr
+
+__all__ = ["__version__", "metaphone3"]
+
+_LIB = Path(__file__).parent
+
+
+def metaphone3(expr: IntoExpr, *, vowels: bool = False, exact: bool = False) -> pl.Expr:
+    """Encode a string expression into a struct of ``primary`` and
+    ``secondary`` Metaphone3 keys.
+
+    ``vowels`` encodes vowels after the first letter and ``exact`` encodes
+    consonants exactly. Null names have null keys, and the struct keeps the
+    name of the input column.
+    """
+    return register_plugin_function(
+        plugin_path=_LIB,
+        function_name="metaphone3",
+        args=expr,
+        kwargs={"vowels": vowels, "exact": exact},
+        is_elementwise=True,
+    )
@@ -0,0 +1,104 @@
+//! [Metaphone 3](metaphone3) keys as a [Polars](polars) expression, in Rust
+//! and as a Python plugin.
+//!
+//! [`metaphone3`] turns a string expression into a struct expression with the
+//! `primary` and `secondary` keys, so names are encoded inside a lazy query
+//! and in parallel with the rest of it. Each Polars worker thread encodes with
+//! its own reused [`Metaphone3`], per mode. Null names have null keys.
+//!
+//! ```
+//! use metaphone3_polars::metaphone3;
+//! use polars::prelude::*;
+//!
+//! let df = df!("name" => ["Smith", "Schmidt"])?;
+//! let keys = df
+//!     .lazy()
+//!     .select([metaphone3(col("name"), false, false).alias("keys")])
+//!     .unnest(by_name(["keys"], true, false), None)
+//!     .collect()?;
+//! assert_eq!(keys.column("primary")?.str()?.get(1), Some("XMT"));
+//! # Ok::<(), PolarsError>(())
+//! ```
+//!
+//! With the `python` feature and built with [maturin](https://www.maturin.rs),
+//! the crate is also the `metaphone3_polars` Python package, whose
+//! `metaphone3` function registers the `plugin` expression with Python
+//! Polars:
+//!
+//! ```python
+//! import polars as pl
+//! from metaphone3_polars import metaphone3
+//!
+//! df = pl.DataFrame({"name": ["Smith", "Schmidt"]})
+//! df.select(metaphone3("name").struct.unnest())
+//! ```
+
+#![warn(clippy::pedantic)]
+
+#[cfg(feature = "python")]
+pub mod plugin;
+
+use std::cell::RefCell;
+
+use metaphone3::{Metaphone3, Mode};
+use polars::prelude::*;
+
+thread_local! {
+    /// One encoder per mode, indexed by [`Mode::index`].
+    static ENCODERS: RefCell<[Metaphone3; 4]> =
+        RefCell::new(Mode::ALL.map(|mode| Metaphone3::new().with_mode(mode)));
+}
+
+/// Encodes a string expression into a struct of `primary` and `secondary`
+/// keys, with the [vowel](Metaphone3::with_encode_vowels) and
+/// [exact](Metaphone3::with_encode_exact) options of [`Metaphone3`].
+///
+/// The struct keeps the name of the input column.
+pub fn metaphone3(expr: Expr, vowels: bool, exact: bool) -> Expr {
+    let mode = Mode::from_options(vowels, exact);
+    expr.map_with_fmt_str(
+        move |column| encode_column(&column, mode).map(Column::from),
+        |_, field| Ok(Field::new(field.name().clone(), output_type())),
+        "metaphone3",
+    )
+}
+
+/// Encodes a string column into a struct column of `primary` and `secondary`
+/// keys in the given mode, as evaluated by [`metaphone3`] and the Python
+/// plugin.
+///
+/// # Errors
+///
+/// Fails if the column is not of type `String`.
+pub fn encode_column(column: &Column, mode: Mode) -> PolarsResult<Series> {
+    let names = column.str()?;
+    let mut primaries = StringChunkedBuilder::new("primary".into(), names.len());
+    let mut secondaries = StringChunkedBuilder::new("secondary".into(), names.len());
+    ENCODERS.with_borrow_mut(|encoders| {
+        let encoder = &mut encoders[mode.index()];
+        for name in names.iter() {
+            if let Some(name) = name {
+                let (primary, secondary) = encoder.encode(name);
+                primaries.append_value(primary);
+                secondaries.append_value(secondary);
+            } else {
+                primaries.append_null();
+                secondaries.append_null();
+            }
+        }
+    });
+    let fields = [
+        primaries.finish().into_series(),
+        secondaries.finish().into_series(),
+    ];
+    StructChunked::from_series(column.name().clone(), names.len(), fields.iter())
+        .map(IntoSeries::into_series)
+}
+
+/// The type of [`metaphone3`]'s output.
+fn output_type() -> DataType {
+    DataType::Struct(vec![
+        Field::new("primary".into(), DataType::String),
+        Field::new("secondary".into(), DataType::String),
+    ])
+}
@@ -0,0 +1,59 @@
+//! The Python plugin: the `metaphone3` expression that Python Polars loads
+//! from the compiled library, and the `metaphone3_polars._internal` module
+//! that makes the library importable.
+//!
+//! The `metaphone3_polars` package in `python/` registers the expression with
+//! `polars.plugins.register_plugin_function`, passing the [`Metaphone3Kwargs`]
+//! as keyword arguments. Rust code should use [`metaphone3`](crate::metaphone3)
+//! instead.
+
+use metaphone3::Mode;
+use polars::prelude::*;
+use pyo3::prelude::*;
+use pyo3_polars::PolarsAllocator;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::{encode_column, output_type};
+
+// Python Polars frees the series a plugin returns, so allocate them with its
+// allocator.
+#[global_allocator]
+static ALLOC: PolarsAllocator = PolarsAllocator::new();
+
+/// Keyword arguments of the plugin expression, the options of
+/// [`Mode::from_options`].
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Metaphone3Kwargs {
+    /// Encode vowels after the first letter.
+    pub vowels: bool,
+    /// Encode consonants exactly.
+    pub exact: bool,
+}
+
+/// The plugin's output field: the struct of keys, named like the input.
+///
+/// # Errors
+///
+/// Fails if the expression has no input.
+pub fn metaphone3_output(inputs: &[Field]) -> PolarsResult<Field> {
+    let [input] = inputs else {
+        polars_bail!(InvalidOperation: "metaphone3 takes 1 argument, got {}", inputs.len());
+    };
+    Ok(Field::new(input.name().clone(), output_type()))
+}
+
+#[polars_expr(output_type_func = metaphone3_output)]
+fn metaphone3(inputs: &[Series], kwargs: Metaphone3Kwargs) -> PolarsResult<Series> {
+    let [names] = inputs else {
+        polars_bail!(InvalidOperation: "metaphone3 takes 1 argument, got {}", inputs.len());
+    };
+    let mode = Mode::from_options(kwargs.vowels, kwargs.exact);
+    encode_column(&names.clone().into_column(), mode)
+}
+
+/// The native module of the Python package.
+#[pymodule]
+fn _internal(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("__version__", env!("CARGO_PKG_VERSION"))
+}
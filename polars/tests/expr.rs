@@ -0,0 +1,58 @@
+use metaphone3::{Metaphone3, Mode};
+use metaphone3_polars::{encode_column, metaphone3};
+use polars::prelude::*;
+
+fn firstnames() -> Vec<String> {
+    std::fs::read_to_string("../testdata/firstnames-us.txt")
+        .expect("run from the polars crate")
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn test_firstnames_all_modes() -> PolarsResult<()> {
+    let names = firstnames();
+    let df = df!("name" => &names)?;
+    let keys = df
+        .lazy()
+        .select(Mode::ALL.map(|mode| {
+            metaphone3(col("name"), mode.encode_vowels(), mode.encode_exact())
+                .alias(mode.to_string())
+        }))
+        .collect()?;
+
+    for mode in Mode::ALL {
+        let mut encoder = Metaphone3::new().with_mode(mode);
+        let keys = keys.column(&mode.to_string())?.struct_()?;
+        let primary = keys.field_by_name("primary")?;
+        let secondary = keys.field_by_name("secondary")?;
+        let (primary, secondary) = (primary.str()?, secondary.str()?);
+        for (i, name) in names.iter().enumerate() {
+            let (p, s) = encoder.encode(name);
+            assert_eq!(primary.get(i), Some(p.as_str()), "{name} {mode}");
+            assert_eq!(secondary.get(i), Some(s.as_str()), "{name} {mode}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_nulls_and_types() -> PolarsResult<()> {
+    let df = df!("name" => [Some("Smith"), None, Some("")])?;
+    let keys = df
+        .lazy()
+        .select([metaphone3(col("name"), false, false)])
+        .unnest(by_name(["name"], true, false), None)
+        .collect()?;
+    assert_eq!(keys.get_column_names(), ["primary", "secondary"]);
+    let primary = keys.column("primary")?.str()?;
+    assert_eq!(primary.get(0), Some("SM0"));
+    assert_eq!(primary.get(1), None);
+    assert_eq!(primary.get(2), Some(""));
+    assert_eq!(keys.column("secondary")?.str()?.get(1), None);
+
+    let numbers = Column::new("n".into(), [1i32, 2]);
+    assert!(encode_column(&numbers, Mode::Enc).is_err());
+    Ok(())
+}
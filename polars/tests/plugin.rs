@@ -0,0 +1,79 @@
+#![cfg(feature = "python")]
+
+use std::collections::BTreeMap;
+
+use metaphone3::{Metaphone3, Mode};
+use metaphone3_polars::plugin::{_polars_plugin_metaphone3, metaphone3_output};
+use polars::prelude::*;
+use polars_ffi::version_0::{CallerContext, SeriesExport, export_series, import_series};
+
+/// Calls the plugin the way Python Polars does: series and pickled keyword
+/// arguments in, a series or the last error out.
+fn call_plugin(names: &Series, kwargs: &BTreeMap<&str, bool>) -> PolarsResult<Series> {
+    let kwargs = serde_pickle::to_vec(kwargs, serde_pickle::SerOptions::new()).unwrap();
+    let mut inputs = [export_series(names)];
+    let mut output = SeriesExport::empty();
+    let mut context = CallerContext::default();
+    unsafe {
+        _polars_plugin_metaphone3(
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            kwargs.as_ptr(),
+            kwargs.len(),
+            &mut output,
+            &mut context,
+        );
+        // The plugin took ownership of the inputs.
+        std::mem::forget(inputs);
+        if output.is_null() {
+            let message = std::ffi::CStr::from_ptr(
+                pyo3_polars::derive::_polars_plugin_get_last_error_message(),
+            );
+            polars_bail!(ComputeError: "{}", message.to_string_lossy());
+        }
+        import_series(output)
+    }
+}
+
+#[test]
+fn test_plugin_modes() -> PolarsResult<()> {
+    let names = Series::new("name".into(), [Some("Jones"), None, Some("Schmidt")]);
+    for mode in Mode::ALL {
+        let kwargs = BTreeMap::from([
+            ("vowels", mode.encode_vowels()),
+            ("exact", mode.encode_exact()),
+        ]);
+        let keys = call_plugin(&names, &kwargs)?;
+        assert_eq!(keys.name(), "name");
+        let keys = keys.struct_()?;
+        let primary = keys.field_by_name("primary")?;
+        let secondary = keys.field_by_name("secondary")?;
+        let mut encoder = Metaphone3::new().with_mode(mode);
+        for i in [0, 2] {
+            let name = names.str()?.get(i).unwrap();
+            let (p, s) = encoder.encode(name);
+            assert_eq!(primary.str()?.get(i), Some(p.as_str()), "{name} {mode}");
+            assert_eq!(secondary.str()?.get(i), Some(s.as_str()), "{name} {mode}");
+        }
+        assert_eq!(primary.str()?.get(1), None);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_plugin_errors() {
+    let names = Series::new("name".into(), ["Smith"]);
+    let missing = BTreeMap::from([("vowels", true)]);
+    let error = call_plugin(&names, &missing).unwrap_err().to_string();
+    assert!(error.contains("could not parse kwargs"), "{error}");
+
+    let numbers = Series::new("n".into(), [1i32, 2]);
+    let kwargs = BTreeMap::from([("vowels", false), ("exact", false)]);
+    assert!(call_plugin(&numbers, &kwargs).is_err());
+
+    let field = Field::new("name".into(), DataType::String);
+    let output = metaphone3_output(std::slice::from_ref(&field)).unwrap();
+    assert_eq!(output.name(), "name");
+    assert!(matches!(output.dtype(), DataType::Struct(fields) if fields.len() == 2));
+    assert!(metaphone3_output(&[]).is_err());
+}
@@ -0,0 +1,42 @@
+from pathlib import Path
+
+import polars as pl
+import pytest
+
+from metaphone3_polars import metaphone3
+
+FIRSTNAMES = Path(__file__).parents[2] / "testdata" / "firstnames-us.txt"
+
+
+def test_keys():
+    df = pl.DataFrame({"name": ["Smith", None, "Schmidt", ""]})
+    keys = df.select(metaphone3("name").struct.unnest())
+    assert keys.columns == ["primary", "secondary"]
+    assert keys["primary"].to_list() == ["SM0", None, "XMT", ""]
+    assert keys["secondary"].to_list() == ["XMT", None, "", ""]
+
+
+@pytest.mark.parametrize(
+    ("vowels", "exact", "key"),
+    [(False, False, "JNS"), (True, False, "JANS"), (False, True, "JNS"), (True, True, "JANS")],
+)
+def test_modes(vowels, exact, key):
+    df = pl.DataFrame({"name": ["Jones"]})
+    keys = df.select(metaphone3(pl.col("name"), vowels=vowels, exact=exact))
+    assert keys["name"].struct.field("primary").to_list() == [key]
+
+
+def test_lazy_firstnames():
+    names = FIRSTNAMES.read_text().splitlines()
+    keys = (
+        pl.LazyFrame({"name": names})
+        .with_columns(key=metaphone3("name").struct.field("primary"))
+        .collect()
+    )
+    assert keys.height == len(names)
+    assert keys.filter(pl.col("key").is_null()).is_empty()
+
+
+def test_not_a_string():
+    with pytest.raises(pl.exceptions.PolarsError):
+        pl.DataFrame({"n": [1, 2]}).select(metaphone3("n"))
@@ -0,0 +1,123 @@
+//! Which letters of a word produced which characters of its keys.
+//!
+//! [`Metaphone3::encode_aligned`] encodes a word like
+//! [`encode`](Metaphone3::encode) and also returns, for every character of the
+//! primary and secondary keys, the range of input characters that produced it.
+//! A rule that reads several letters at once ("TH", "SCH") reports all of them.
+//! Letters that no output character came from are *silent*, like the vowels
+//! after the first one when vowels are not encoded, or the "GH" of "night".
+//!
+//! Ranges are in `char`s of the input word, so a UI can highlight the letters
+//! behind a match:
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//!
+//! let mut encoder = Metaphone3::new();
+//! let aligned = encoder.encode_aligned("Smith");
+//! assert_eq!(aligned.primary, "SM0");
+//! assert_eq!(aligned.primary_spans(), [0..1, 1..2, 3..5]);
+//! assert_eq!(aligned.secondary, "XMT");
+//! assert!(aligned.is_silent(2)); // the "I"
+//! ```
+//!
+//! Keys from an [exception dictionary](crate::exceptions) map to the whole
+//! entry they came from: the word, prefix or suffix.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{Metaphone3, String};
+
+/// Spans in `in_buf` of the output characters, as `(start, end)`.
+#[derive(Clone, Default)]
+pub(crate) struct Spans {
+    pub(crate) primary: Vec<(usize, usize)>,
+    pub(crate) secondary: Vec<(usize, usize)>,
+}
+
+/// The keys of a word together with where each of their characters came from.
+/// Created by [`Metaphone3::encode_aligned`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment {
+    /// The primary key, as returned by [`Metaphone3::encode`].
+    pub primary: String,
+    /// The secondary key, as returned by [`Metaphone3::encode`]; empty if it
+    /// equals the primary key.
+    pub secondary: String,
+    primary_spans: Vec<Range<usize>>,
+    secondary_spans: Vec<Range<usize>>,
+    silent: Vec<bool>,
+}
+
+impl Alignment {
+    /// The input character range behind each character of the primary key.
+    #[must_use]
+    pub fn primary_spans(&self) -> &[Range<usize>] {
+        &self.primary_spans
+    }
+
+    /// The input character range behind each character of the secondary key;
+    /// empty if there is no secondary key.
+    #[must_use]
+    pub fn secondary_spans(&self) -> &[Range<usize>] {
+        &self.secondary_spans
+    }
+
+    /// Returns true if the input character at `index` produced no character
+    /// of either key. Out-of-range indices are not silent.
+    #[must_use]
+    pub fn is_silent(&self, index: usize) -> bool {
+        self.silent.get(index).copied().unwrap_or(false)
+    }
+
+    /// Indices of the silent input characters, in order.
+    pub fn silent(&self) -> impl Iterator<Item = usize> + '_ {
+        self.silent
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &silent)| silent.then_some(index))
+    }
+}
+
+impl Metaphone3 {
+    /// Encodes a word like [`encode`](Self::encode) and aligns each key
+    /// character with the input characters it came from. See the
+    /// [`align`](crate::align) module.
+    pub fn encode_aligned(&mut self, word: &str) -> Alignment {
+        self.spans = Some(Spans::default());
+        self.side_output = true;
+        let (primary, secondary) = self.encode(word);
+        self.side_output = false;
+        let recorded = self.spans.take().unwrap_or_default();
+
+        // Uppercasing can turn one character into several, so map each
+        // position of `in_buf` back to the input character it came from.
+        let mut source = Vec::with_capacity(self.in_buf.len());
+        let mut chars = 0;
+        for (index, c) in word.chars().enumerate() {
+            source.extend(c.to_uppercase().map(|_| index));
+            chars = index + 1;
+        }
+        let spans = |spans: &[(usize, usize)], len: usize| -> Vec<Range<usize>> {
+            spans[..len]
+                .iter()
+                .map(|&(start, end)| source[start]..source[end - 1] + 1)
+                .collect()
+        };
+        let primary_spans = spans(&recorded.primary, primary.chars().count());
+        let secondary_spans = spans(&recorded.secondary, secondary.chars().count());
+
+        let mut silent = alloc::vec![true; chars];
+        for span in primary_spans.iter().chain(&secondary_spans) {
+            silent[span.clone()].fill(false);
+        }
+        Alignment {
+            primary,
+            secondary,
+            primary_spans,
+            secondary_spans,
+            silent,
+        }
+    }
+}
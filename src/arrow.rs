@@ -0,0 +1,89 @@
+//! Encoding [Apache Arrow](https://arrow.apache.org) string columns.
+//!
+//! Requires the `arrow` feature. [`Metaphone3::encode_string_array`] encodes
+//! every value of a `StringArray` or `LargeStringArray` with the one encoder and
+//! returns the primary and secondary keys as two `StringArray`s of the same
+//! length. Null values stay null in both outputs.
+//!
+//! ```
+//! use arrow_array::{Array, StringArray};
+//! use metaphone3::Metaphone3;
+//!
+//! let names = StringArray::from(vec![Some("Smith"), None, Some("Schmidt")]);
+//! let (primary, secondary) = Metaphone3::new().encode_string_array(&names);
+//! assert_eq!(primary.value(0), "SM0");
+//! assert!(primary.is_null(1) && secondary.is_null(1));
+//! assert_eq!((primary.value(2), secondary.value(2)), ("XMT", ""));
+//! ```
+//!
+//! [`encode_array`](Metaphone3::encode_array) takes a `&dyn Array` of any
+//! string type, `Utf8View` included, which is what a query engine's scalar
+//! function receives. The `metaphone3-datafusion` crate in `datafusion/` wraps
+//! it as a `DataFusion` SQL function, `metaphone3(name)`.
+
+use arrow_array::builder::StringBuilder;
+use arrow_array::cast::AsArray;
+use arrow_array::{Array, GenericStringArray, OffsetSizeTrait, StringArray};
+use arrow_schema::{ArrowError, DataType};
+
+use crate::Metaphone3;
+
+/// Bytes reserved per key in the output arrays: most keys are 4 ASCII letters
+/// or shorter.
+const KEY_BYTES: usize = 4;
+
+impl Metaphone3 {
+    /// Encodes every value of a `StringArray` or `LargeStringArray` into
+    /// arrays of primary and secondary keys. See the [`arrow`](crate::arrow)
+    /// module.
+    pub fn encode_string_array<O: OffsetSizeTrait>(
+        &mut self,
+        array: &GenericStringArray<O>,
+    ) -> (StringArray, StringArray) {
+        self.encode_values(array.len(), array.iter())
+    }
+
+    /// Encodes an array of type `Utf8`, `LargeUtf8` or `Utf8View` like
+    /// [`encode_string_array`](Self::encode_string_array).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArrowError::InvalidArgumentError`] for arrays of any other
+    /// type.
+    pub fn encode_array(
+        &mut self,
+        array: &dyn Array,
+    ) -> Result<(StringArray, StringArray), ArrowError> {
+        match array.data_type() {
+            DataType::Utf8 => Ok(self.encode_string_array(array.as_string::<i32>())),
+            DataType::LargeUtf8 => Ok(self.encode_string_array(array.as_string::<i64>())),
+            DataType::Utf8View => {
+                let array = array.as_string_view();
+                Ok(self.encode_values(array.len(), array.iter()))
+            }
+            other => Err(ArrowError::InvalidArgumentError(format!(
+                "metaphone3 expects a string array, got {other}"
+            ))),
+        }
+    }
+
+    fn encode_values<'a>(
+        &mut self,
+        len: usize,
+        values: impl Iterator<Item = Option<&'a str>>,
+    ) -> (StringArray, StringArray) {
+        let mut primaries = StringBuilder::with_capacity(len, len * KEY_BYTES);
+        let mut secondaries = StringBuilder::with_capacity(len, len * KEY_BYTES);
+        for value in values {
+            if let Some(word) = value {
+                let (primary, secondary) = self.encode(word);
+                primaries.append_value(primary);
+                secondaries.append_value(secondary);
+            } else {
+                primaries.append_null();
+                secondaries.append_null();
+            }
+        }
+        (primaries.finish(), secondaries.finish())
+    }
+}
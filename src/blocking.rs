@@ -0,0 +1,231 @@
+//! Blocking keys for record linkage.
+//!
+//! Comparing every pair of records does not scale, so entity resolution first
+//! puts records into *blocks* and only compares records within a block. A
+//! [`BlockingStrategy`] declares how a blocking key is built from the named
+//! fields of a [`Record`], part by part:
+//!
+//! - [`primary`](BlockingStrategy::primary): the primary Metaphone 3 key,
+//! - [`keys`](BlockingStrategy::keys): the primary *and* secondary keys, as
+//!   alternatives,
+//! - [`prefix`](BlockingStrategy::prefix): the first letters of the value,
+//! - [`exact`](BlockingStrategy::exact): the whole value.
+//!
+//! The parts are joined with a separator (`|` by default). A part with
+//! alternatives yields one blocking key per alternative, so a record gets the
+//! cartesian product of all of them: a name with a secondary key lands in the
+//! blocks of both pronunciations.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::blocking::BlockingStrategy;
+//!
+//! // Last name keys plus the initial of the first name.
+//! let mut strategy = BlockingStrategy::new(Metaphone3::new())
+//!     .keys("last_name")
+//!     .prefix("first_name", 1);
+//!
+//! let record = [("first_name", "John"), ("last_name", "Smith")];
+//! assert_eq!(strategy.block_keys(&record[..]), ["SM0|J", "XMT|J"]);
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+use crate::Metaphone3;
+
+/// A record with named fields.
+///
+/// Implemented for maps from field names to values (`HashMap` needs the `std`
+/// feature) and for slices of `(name, value)` pairs.
+pub trait Record {
+    /// The value of the field `name`, if the record has it.
+    fn field(&self, name: &str) -> Option<&str>;
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> Record for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<str>,
+    S: BuildHasher,
+{
+    fn field(&self, name: &str) -> Option<&str> {
+        self.get(name).map(AsRef::as_ref)
+    }
+}
+
+impl<K, V> Record for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: AsRef<str>,
+{
+    fn field(&self, name: &str) -> Option<&str> {
+        self.get(name).map(AsRef::as_ref)
+    }
+}
+
+impl<K, V> Record for [(K, V)]
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    fn field(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(key, _)| key.as_ref() == name)
+            .map(|(_, value)| value.as_ref())
+    }
+}
+
+/// What a part of a blocking key takes from its field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartKind {
+    Primary,
+    Keys,
+    Prefix(usize),
+    Exact,
+}
+
+/// One part of a blocking key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Part {
+    field: String,
+    kind: PartKind,
+}
+
+/// A declared way of building blocking keys. See the
+/// [module documentation](self).
+pub struct BlockingStrategy {
+    encoder: Metaphone3,
+    parts: Vec<Part>,
+    separator: char,
+}
+
+impl BlockingStrategy {
+    /// Creates a strategy without parts that encodes fields with `encoder`.
+    #[must_use]
+    pub fn new(encoder: Metaphone3) -> Self {
+        BlockingStrategy {
+            encoder,
+            parts: Vec::new(),
+            separator: '|',
+        }
+    }
+
+    /// Adds the primary key of `field`.
+    #[must_use]
+    pub fn primary(self, field: &str) -> Self {
+        self.part(field, PartKind::Primary)
+    }
+
+    /// Adds the primary and, if there is one, the secondary key of `field`,
+    /// each giving its own blocking key.
+    #[must_use]
+    pub fn keys(self, field: &str) -> Self {
+        self.part(field, PartKind::Keys)
+    }
+
+    /// Adds the first `len` characters of `field`, trimmed and uppercased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is 0, since an empty prefix would leave every record
+    /// without blocking keys.
+    #[must_use]
+    pub fn prefix(self, field: &str, len: usize) -> Self {
+        assert!(len > 0, "blocking prefix length must be at least 1");
+        self.part(field, PartKind::Prefix(len))
+    }
+
+    /// Adds the whole value of `field`, trimmed and uppercased.
+    #[must_use]
+    pub fn exact(self, field: &str) -> Self {
+        self.part(field, PartKind::Exact)
+    }
+
+    /// Sets the character joining the parts of a key. Defaults to `|`.
+    #[must_use]
+    pub fn with_separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    fn part(mut self, field: &str, kind: PartKind) -> Self {
+        self.parts.push(Part {
+            field: field.to_owned(),
+            kind,
+        });
+        self
+    }
+
+    /// Builds all blocking keys of `record`, starting with the one made of
+    /// primary keys only.
+    ///
+    /// A record gets no keys at all if one of the parts is empty: a missing
+    /// field, a blank value, or a value without encodable letters. Such
+    /// records would otherwise all share one oversized block. For the same
+    /// reason a strategy without parts gives no keys.
+    pub fn block_keys<R: Record + ?Sized>(&mut self, record: &R) -> Vec<String> {
+        if self.parts.is_empty() {
+            return Vec::new();
+        }
+        let mut keys = vec![String::new()];
+        for (n, part) in self.parts.iter().enumerate() {
+            let alternatives = Self::alternatives(&mut self.encoder, part, record);
+            if alternatives.is_empty() {
+                return Vec::new();
+            }
+            let mut next = Vec::with_capacity(keys.len() * alternatives.len());
+            for key in &keys {
+                for alt in &alternatives {
+                    let mut key = key.clone();
+                    if n > 0 {
+                        key.push(self.separator);
+                    }
+                    key.push_str(alt);
+                    next.push(key);
+                }
+            }
+            keys = next;
+        }
+        keys
+    }
+
+    /// The non-empty values a part takes for `record`.
+    fn alternatives<R: Record + ?Sized>(
+        encoder: &mut Metaphone3,
+        part: &Part,
+        record: &R,
+    ) -> Vec<String> {
+        let Some(value) = record.field(&part.field).map(str::trim) else {
+            return Vec::new();
+        };
+        let mut alternatives: Vec<String> = match part.kind {
+            PartKind::Primary => vec![encoder.encode(value).0.into()],
+            PartKind::Keys => {
+                let (primary, secondary) = encoder.encode(value);
+                vec![primary.into(), secondary.into()]
+            }
+            PartKind::Prefix(len) => {
+                vec![
+                    value
+                        .chars()
+                        .flat_map(char::to_uppercase)
+                        .take(len)
+                        .collect(),
+                ]
+            }
+            PartKind::Exact => vec![value.to_uppercase()],
+        };
+        alternatives.retain(|alt| !alt.is_empty());
+        alternatives
+    }
+}
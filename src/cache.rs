@@ -0,0 +1,239 @@
+//! An encoder that remembers the keys of recently encoded words.
+//!
+//! Name columns repeat themselves: a few thousand common surnames cover most
+//! rows of a typical table. [`CachedMetaphone3`] wraps a [`Metaphone3`] with a
+//! bounded least-recently-used cache, so repeated names skip the rule engine
+//! and only pay for a hash lookup.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::cache::CachedMetaphone3;
+//!
+//! let mut encoder = CachedMetaphone3::new(Metaphone3::new(), 1000);
+//! for name in ["Smith", "Jones", "Smith", "Smith"] {
+//!     encoder.encode(name);
+//! }
+//! let stats = encoder.stats();
+//! assert_eq!((stats.hits, stats.misses), (2, 2));
+//! assert_eq!(encoder.encode("Smith"), Metaphone3::new().encode("Smith"));
+//! ```
+//!
+//! Entries are keyed by the word and the encoder's
+//! [fingerprint](crate::fingerprint), so one cache can serve several
+//! configurations through [`set_encoder`](CachedMetaphone3::set_encoder).
+//! Lookups and evictions take constant time.
+
+use std::collections::HashMap;
+
+use crate::{Metaphone3, String};
+
+/// Marks the end of the recency list.
+const NIL: usize = usize::MAX;
+
+/// Hit and miss counts of a [`CachedMetaphone3`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Words answered from the cache.
+    pub hits: u64,
+    /// Words that had to be encoded.
+    pub misses: u64,
+    /// Entries dropped to make room for new ones.
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache, or 0.0 before the first
+    /// lookup.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // a ratio does not need every bit
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A cached entry, linked into the recency list.
+struct Node {
+    fingerprint: u64,
+    word: Box<str>,
+    keys: (String, String),
+    /// Next more recently used entry.
+    prev: usize,
+    /// Next less recently used entry.
+    next: usize,
+}
+
+/// A [`Metaphone3`] with a bounded LRU cache of keys. See the
+/// [module documentation](self).
+pub struct CachedMetaphone3 {
+    encoder: Metaphone3,
+    fingerprint: u64,
+    capacity: usize,
+    /// Positions in `nodes`, by fingerprint and then by word.
+    index: HashMap<u64, HashMap<Box<str>, usize>>,
+    nodes: Vec<Node>,
+    /// Most recently used entry.
+    head: usize,
+    /// Least recently used entry, the next to be evicted.
+    tail: usize,
+    stats: CacheStats,
+}
+
+impl CachedMetaphone3 {
+    /// Wraps `encoder` with a cache of at most `capacity` words. A capacity of
+    /// 0 disables caching.
+    #[must_use]
+    pub fn new(encoder: Metaphone3, capacity: usize) -> Self {
+        CachedMetaphone3 {
+            fingerprint: encoder.fingerprint().to_u64(),
+            encoder,
+            capacity,
+            index: HashMap::new(),
+            nodes: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Encodes a word like [`Metaphone3::encode`], from the cache if it was
+    /// encoded recently.
+    pub fn encode(&mut self, word: &str) -> (String, String) {
+        let cached = self
+            .index
+            .get(&self.fingerprint)
+            .and_then(|words| words.get(word))
+            .copied();
+        if let Some(node) = cached {
+            self.stats.hits += 1;
+            self.unlink(node);
+            self.push_front(node);
+            return self.nodes[node].keys.clone();
+        }
+
+        self.stats.misses += 1;
+        let keys = self.encoder.encode(word);
+        if self.capacity > 0 {
+            self.insert(word, keys.clone());
+        }
+        keys
+    }
+
+    /// The wrapped encoder.
+    #[must_use]
+    pub fn encoder(&self) -> &Metaphone3 {
+        &self.encoder
+    }
+
+    /// Replaces the wrapped encoder. Cached keys of other configurations are
+    /// kept, and are used again if an encoder with their configuration is set
+    /// later.
+    ///
+    /// Custom rules are told apart by their
+    /// [version](crate::rules::RuleSet::with_version), so give rule sets that
+    /// emit different keys different versions, or call [`clear`](Self::clear).
+    pub fn set_encoder(&mut self, encoder: Metaphone3) {
+        self.fingerprint = encoder.fingerprint().to_u64();
+        self.encoder = encoder;
+    }
+
+    /// Maximum number of cached words.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of cached words.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if no words are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Hit, miss and eviction counts since creation or the last
+    /// [`reset_stats`](Self::reset_stats).
+    #[must_use]
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Sets all counts back to 0.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+
+    /// Drops all cached words. The statistics are kept.
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.nodes.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// Caches `keys` for `word`, evicting the least recently used entry if
+    /// the cache is full.
+    fn insert(&mut self, word: &str, keys: (String, String)) {
+        let node = Node {
+            fingerprint: self.fingerprint,
+            word: word.into(),
+            keys,
+            prev: NIL,
+            next: NIL,
+        };
+        let slot = if self.nodes.len() < self.capacity {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        } else {
+            let slot = self.tail;
+            self.unlink(slot);
+            let old = std::mem::replace(&mut self.nodes[slot], node);
+            if let Some(words) = self.index.get_mut(&old.fingerprint) {
+                words.remove(&old.word);
+                if words.is_empty() {
+                    self.index.remove(&old.fingerprint);
+                }
+            }
+            self.stats.evictions += 1;
+            slot
+        };
+        self.index
+            .entry(self.fingerprint)
+            .or_default()
+            .insert(self.nodes[slot].word.clone(), slot);
+        self.push_front(slot);
+    }
+
+    fn unlink(&mut self, node: usize) {
+        let Node { prev, next, .. } = self.nodes[node];
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.nodes[prev].next = next;
+        }
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.nodes[next].prev = prev;
+        }
+    }
+
+    fn push_front(&mut self, node: usize) {
+        self.nodes[node].prev = NIL;
+        self.nodes[node].next = self.head;
+        if self.head == NIL {
+            self.tail = node;
+        } else {
+            self.nodes[self.head].prev = node;
+        }
+        self.head = node;
+    }
+}
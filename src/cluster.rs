@@ -0,0 +1,183 @@
+//! Key-collision clustering of similar-sounding values.
+//!
+//! This is the "metaphone3" keying function of `OpenRefine`'s key-collision
+//! clustering: values whose Metaphone 3 keys collide land in the same
+//! [`Cluster`], which reports how often each distinct value occurred and
+//! suggests the most frequent one as the canonical spelling.
+//!
+//! Clusters are formed on primary keys only. Secondary keys are not merged on,
+//! since merging is transitive: one ambiguous value with primary key `A` and
+//! secondary key `B` would join every `A` value with every `B` value. With
+//! [`Clusterer::with_secondary_keys`], values whose secondary keys connect
+//! them to a cluster are instead reported as its
+//! [`candidates`](Cluster::candidates), for review.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::cluster::Clusterer;
+//!
+//! let names = ["Smith", "Smyth", "Smith", "Schmidt", "Jones", "Smithe"];
+//! let clusters = Clusterer::new(Metaphone3::new()).cluster(names);
+//!
+//! assert_eq!(clusters.len(), 1);
+//! assert_eq!(clusters[0].key, "SM0");
+//! assert_eq!(clusters[0].canonical(), "Smith");
+//! assert_eq!(clusters[0].rows, 4);
+//! assert_eq!(clusters[0].values.len(), 3);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{Metaphone3, String};
+
+/// A distinct value and the number of times it occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterValue {
+    /// The value, as given.
+    pub value: std::string::String,
+    /// Number of occurrences.
+    pub count: usize,
+}
+
+/// A group of values with colliding keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    /// The primary key of the canonical value.
+    pub key: String,
+    /// The distinct values, most frequent first (ties in input order).
+    pub values: Vec<ClusterValue>,
+    /// Total number of occurrences across all values.
+    pub rows: usize,
+    /// Values outside the cluster that match one of its values through a
+    /// secondary key, most frequent first (ties in input order). Only filled
+    /// with [`Clusterer::with_secondary_keys`].
+    pub candidates: Vec<ClusterValue>,
+}
+
+impl Cluster {
+    /// The suggested canonical value: the most frequent one.
+    #[must_use]
+    pub fn canonical(&self) -> &str {
+        &self.values[0].value
+    }
+}
+
+/// Groups values by their Metaphone 3 keys.
+pub struct Clusterer {
+    encoder: Metaphone3,
+    secondary_keys: bool,
+    min_values: usize,
+}
+
+impl Clusterer {
+    /// Creates a clusterer that keys values with `encoder`.
+    #[must_use]
+    pub fn new(encoder: Metaphone3) -> Self {
+        Clusterer {
+            encoder,
+            secondary_keys: false,
+            min_values: 2,
+        }
+    }
+
+    /// Also reports values that match a cluster through a secondary key as
+    /// its [`candidates`](Cluster::candidates). Off by default.
+    #[must_use]
+    pub fn with_secondary_keys(mut self, merge: bool) -> Self {
+        self.secondary_keys = merge;
+        self
+    }
+
+    /// Only reports clusters with at least this many distinct values.
+    /// Defaults to 2, so values without variants are left out.
+    #[must_use]
+    pub fn with_min_values(mut self, min_values: usize) -> Self {
+        self.min_values = min_values;
+        self
+    }
+
+    /// Clusters `values` by primary key, largest clusters (by rows) first.
+    ///
+    /// Each distinct value is encoded once. Values without any encodable
+    /// letters (empty keys) are never clustered.
+    pub fn cluster<'a, I>(&mut self, values: I) -> Vec<Cluster>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        // Distinct values in first-seen order, with their counts.
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut distinct: Vec<(&str, usize)> = Vec::new();
+        for value in values {
+            let i = *index.entry(value).or_insert_with(|| {
+                distinct.push((value, 0));
+                distinct.len() - 1
+            });
+            distinct[i].1 += 1;
+        }
+
+        let keys: Vec<(String, String)> = distinct
+            .iter()
+            .map(|&(value, _)| self.encoder.encode(value))
+            .collect();
+
+        // Values by primary key, in first-seen order.
+        let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, (primary, _)) in keys.iter().enumerate() {
+            if !primary.is_empty() {
+                groups.entry(primary.as_str()).or_default().push(i);
+            }
+        }
+        // Values by every key they have, to find candidates.
+        let mut postings: HashMap<&str, Vec<usize>> = HashMap::new();
+        if self.secondary_keys {
+            for (i, (primary, secondary)) in keys.iter().enumerate() {
+                for key in [primary, secondary] {
+                    if !key.is_empty() {
+                        postings.entry(key.as_str()).or_default().push(i);
+                    }
+                }
+            }
+        }
+
+        let by_count = |members: &mut Vec<usize>| {
+            // Sorting by position first keeps input order among equally
+            // frequent values.
+            members.sort_unstable();
+            members.dedup();
+            members.sort_by_key(|&i| std::cmp::Reverse(distinct[i].1));
+        };
+        let values_of = |members: &[usize]| -> Vec<ClusterValue> {
+            members
+                .iter()
+                .map(|&i| ClusterValue {
+                    value: distinct[i].0.to_owned(),
+                    count: distinct[i].1,
+                })
+                .collect()
+        };
+        let mut clusters: Vec<Cluster> = groups
+            .into_iter()
+            .filter(|(_, members)| members.len() >= self.min_values)
+            .map(|(key, mut members)| {
+                let mut candidates: Vec<usize> = members
+                    .iter()
+                    .flat_map(|&i| [&keys[i].0, &keys[i].1])
+                    .filter_map(|key| postings.get(key.as_str()))
+                    .flatten()
+                    .copied()
+                    .filter(|&j| keys[j].0 != key)
+                    .collect();
+                by_count(&mut members);
+                by_count(&mut candidates);
+                Cluster {
+                    key: key.into(),
+                    rows: members.iter().map(|&i| distinct[i].1).sum(),
+                    values: values_of(&members),
+                    candidates: values_of(&candidates),
+                }
+            })
+            .collect();
+        clusters.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.key.cmp(&b.key)));
+        clusters
+    }
+}
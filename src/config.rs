@@ -0,0 +1,216 @@
+//! The shareable, immutable counterpart of [`Metaphone3`].
+
+use alloc::sync::Arc;
+
+use crate::exceptions::ExceptionDictionary;
+use crate::fingerprint::Fingerprint;
+use crate::packed::PackedKey;
+use crate::rules::RuleSet;
+use crate::validate::EncodeError;
+use crate::{Metaphone3, Mode, String};
+
+/// An encoder configuration that encodes through a shared reference.
+///
+/// [`Metaphone3::encode`] takes `&mut self` because the encoder keeps its
+/// working buffers between calls. A `Metaphone3Config` holds only the
+/// configuration, is [`Send`] + [`Sync`], and encodes with `&self`, so one
+/// instance can be put in an `Arc` or a `static` and used from any number of
+/// threads or async tasks at once.
+///
+/// With the `std` feature each thread reuses one scratch encoder, so encoding
+/// through a `Metaphone3Config` allocates no more than [`Metaphone3::encode`]
+/// does. Without `std` every call encodes with fresh buffers. Where one thread
+/// encodes many words in a row, [`encoder`](Self::encoder) gives a
+/// [`Metaphone3`] for the `&mut` fast path.
+///
+/// ```
+/// use std::sync::Arc;
+/// use metaphone3::Metaphone3Config;
+///
+/// let config = Arc::new(Metaphone3Config::new().with_encode_vowels(true));
+/// let handles: Vec<_> = ["Smith", "Schmidt"]
+///     .into_iter()
+///     .map(|word| {
+///         let config = Arc::clone(&config);
+///         std::thread::spawn(move || config.encode(word))
+///     })
+///     .collect();
+/// let keys: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+/// assert_eq!(keys[0], ("SMA0".into(), "XMAT".into()));
+/// ```
+#[derive(Clone, Default)]
+pub struct Metaphone3Config {
+    encode_vowels: bool,
+    encode_exact: bool,
+    max_input_length: Option<usize>,
+    exceptions: Option<Arc<ExceptionDictionary>>,
+    rules: Option<Arc<RuleSet>>,
+}
+
+impl Metaphone3Config {
+    /// Creates a configuration with default settings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the option to encode vowels.
+    #[must_use]
+    pub fn with_encode_vowels(mut self, encode: bool) -> Self {
+        self.encode_vowels = encode;
+        self
+    }
+
+    /// Sets the option for more exact encoding.
+    #[must_use]
+    pub fn with_encode_exact(mut self, encode: bool) -> Self {
+        self.encode_exact = encode;
+        self
+    }
+
+    /// Sets both the vowel and exact options from a [`Mode`].
+    #[must_use]
+    pub fn with_mode(self, mode: Mode) -> Self {
+        self.with_encode_vowels(mode.encode_vowels())
+            .with_encode_exact(mode.encode_exact())
+    }
+
+    /// Sets the maximum input length for [`try_encode`](Self::try_encode), as
+    /// for [`Metaphone3::with_max_input_length`].
+    #[must_use]
+    pub fn with_max_input_length(mut self, max: usize) -> Self {
+        self.max_input_length = Some(max);
+        self
+    }
+
+    /// Sets an exception dictionary, as for
+    /// [`Metaphone3::with_exceptions`].
+    #[must_use]
+    pub fn with_exceptions(mut self, exceptions: impl Into<Arc<ExceptionDictionary>>) -> Self {
+        self.exceptions = Some(exceptions.into());
+        self
+    }
+
+    /// Sets custom spelling rules, as for [`Metaphone3::with_rules`].
+    #[must_use]
+    pub fn with_rules(mut self, rules: impl Into<Arc<RuleSet>>) -> Self {
+        self.rules = Some(rules.into());
+        self
+    }
+
+    /// Returns the [`Mode`] this configuration is for.
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        Mode::from_options(self.encode_vowels, self.encode_exact)
+    }
+
+    /// Returns the [`Fingerprint`] of this configuration, the same as that of
+    /// its [`encoder`](Self::encoder).
+    #[must_use]
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.encoder().fingerprint()
+    }
+
+    /// Returns a new [`Metaphone3`] with this configuration.
+    #[must_use]
+    pub fn encoder(&self) -> Metaphone3 {
+        let mut encoder = Metaphone3::new();
+        self.configure(&mut encoder);
+        encoder
+    }
+
+    /// Encodes a word like [`Metaphone3::encode`].
+    #[must_use]
+    pub fn encode(&self, word: &str) -> (String, String) {
+        self.with_scratch(|encoder| encoder.encode(word))
+    }
+
+    /// Encodes ISO-8859-1 bytes like [`Metaphone3::encode_latin1`].
+    #[must_use]
+    pub fn encode_latin1(&self, word: &[u8]) -> (String, String) {
+        self.with_scratch(|encoder| encoder.encode_latin1(word))
+    }
+
+    /// Encodes a word like [`Metaphone3::encode_packed`].
+    #[must_use]
+    pub fn encode_packed(&self, word: &str) -> (PackedKey, PackedKey) {
+        self.with_scratch(|encoder| encoder.encode_packed(word))
+    }
+
+    /// Encodes a word like [`Metaphone3::try_encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] for input that cannot be encoded, see
+    /// [`Metaphone3::try_encode`].
+    pub fn try_encode(&self, word: &str) -> Result<(String, String), EncodeError> {
+        self.with_scratch(|encoder| encoder.try_encode(word))
+    }
+
+    fn configure(&self, encoder: &mut Metaphone3) {
+        encoder.encode_vowels = self.encode_vowels;
+        encoder.encode_exact = self.encode_exact;
+        encoder.max_input_length = self.max_input_length.unwrap_or(usize::MAX);
+        encoder.exceptions.clone_from(&self.exceptions);
+        encoder.rules.clone_from(&self.rules);
+    }
+
+    /// Runs `f` on this thread's scratch encoder, configured like `self`.
+    ///
+    /// Falls back to a fresh encoder when the scratch one is already in use,
+    /// which happens when a custom rule encodes through a shared
+    /// configuration itself.
+    #[cfg(feature = "std")]
+    fn with_scratch<R>(&self, f: impl FnOnce(&mut Metaphone3) -> R) -> R {
+        use core::cell::RefCell;
+
+        std::thread_local! {
+            static SCRATCH: RefCell<Metaphone3> = RefCell::new(Metaphone3::new());
+        }
+
+        let mut f = Some(f);
+        SCRATCH
+            .try_with(|scratch| {
+                let mut encoder = scratch.try_borrow_mut().ok()?;
+                let f = f.take()?;
+                self.configure(&mut encoder);
+                let result = f(&mut encoder);
+                // Do not keep the dictionary and rules alive from a thread-local.
+                encoder.exceptions = None;
+                encoder.rules = None;
+                Some(result)
+            })
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| f.expect("scratch encoder unused")(&mut self.encoder()))
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn with_scratch<R>(&self, f: impl FnOnce(&mut Metaphone3) -> R) -> R {
+        f(&mut self.encoder())
+    }
+}
+
+impl From<&Metaphone3> for Metaphone3Config {
+    fn from(encoder: &Metaphone3) -> Self {
+        Metaphone3Config {
+            encode_vowels: encoder.encode_vowels,
+            encode_exact: encoder.encode_exact,
+            max_input_length: Some(encoder.max_input_length).filter(|&max| max != usize::MAX),
+            exceptions: encoder.exceptions.clone(),
+            rules: encoder.rules.clone(),
+        }
+    }
+}
+
+impl From<Metaphone3> for Metaphone3Config {
+    fn from(encoder: Metaphone3) -> Self {
+        Metaphone3Config::from(&encoder)
+    }
+}
+
+impl From<Metaphone3Config> for Metaphone3 {
+    fn from(config: Metaphone3Config) -> Self {
+        config.encoder()
+    }
+}
@@ -0,0 +1,104 @@
+//! The field syntax of RFC 4180 (CSV), shared by the line-based formats.
+//!
+//! A field that contains the delimiter, a double quote or a line break is
+//! written in double quotes, with each double quote inside doubled. Every
+//! other field is written as is.
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// A field with a stray double quote, such as `O"Brien` or `"a"b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BadQuotes;
+
+/// Returns true if `record` ends inside a quoted field, which then continues
+/// on the next line.
+pub(crate) fn is_open(record: &str) -> bool {
+    record.matches('"').count() % 2 == 1
+}
+
+/// Splits a record into its fields, unquoting quoted ones. The record must not
+/// be [open](is_open).
+pub(crate) fn fields(record: &str, delimiter: char) -> Fields<'_> {
+    Fields {
+        rest: Some(record),
+        delimiter,
+    }
+}
+
+/// Iterator over the fields of a record. Created by [`fields`].
+pub(crate) struct Fields<'a> {
+    rest: Option<&'a str>,
+    delimiter: char,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Cow<'a, str>, BadQuotes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.take()?;
+        let Some(quoted) = rest.strip_prefix('"') else {
+            let (field, rest) = match rest.split_once(self.delimiter) {
+                Some((field, rest)) => (field, Some(rest)),
+                None => (rest, None),
+            };
+            self.rest = rest;
+            return Some(if field.contains('"') {
+                Err(BadQuotes)
+            } else {
+                Ok(Cow::Borrowed(field))
+            });
+        };
+
+        // Only fields with doubled quotes need a copy.
+        let mut unescaped: Option<String> = None;
+        let mut pos = 0;
+        loop {
+            let Some(quote) = quoted[pos..].find('"').map(|i| pos + i) else {
+                return Some(Err(BadQuotes));
+            };
+            if quoted[quote + 1..].starts_with('"') {
+                unescaped
+                    .get_or_insert_with(String::new)
+                    .push_str(&quoted[pos..=quote]);
+                pos = quote + 2;
+                continue;
+            }
+            let field = match unescaped {
+                Some(mut field) => {
+                    field.push_str(&quoted[pos..quote]);
+                    Cow::Owned(field)
+                }
+                None => Cow::Borrowed(&quoted[..quote]),
+            };
+            let after = &quoted[quote + 1..];
+            if !after.is_empty() {
+                let Some(rest) = after.strip_prefix(self.delimiter) else {
+                    return Some(Err(BadQuotes));
+                };
+                self.rest = Some(rest);
+            }
+            return Some(Ok(field));
+        }
+    }
+}
+
+/// Displays a field, quoted if it needs to be.
+pub(crate) struct Quoted<'a>(pub(crate) &'a str, pub(crate) char);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Quoted(field, delimiter) = *self;
+        if !field.contains([delimiter, '"', '\n', '\r']) {
+            return f.write_str(field);
+        }
+        f.write_str("\"")?;
+        for (i, part) in field.split('"').enumerate() {
+            if i > 0 {
+                f.write_str("\"\"")?;
+            }
+            f.write_str(part)?;
+        }
+        f.write_str("\"")
+    }
+}
@@ -0,0 +1,206 @@
+//! Keys together with how they were made.
+//!
+//! [`Metaphone3::encode_detailed`] encodes a word like
+//! [`encode`](Metaphone3::encode) and also reports
+//!
+//! - the *full-length* keys, before they are cut to
+//!   [`METAPH_MAX_LENGTH`] characters, and so whether either key was
+//!   truncated, and
+//! - the [`Heuristics`] about the word's origin that shaped the keys.
+//!
+//! Truncated keys only compare the beginning of long words, so a matcher may
+//! want to trust their matches less:
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::detailed::Heuristics;
+//!
+//! let mut encoder = Metaphone3::new();
+//! let detailed = encoder.encode_detailed("Wagner");
+//! assert_eq!((detailed.primary.as_str(), detailed.secondary.as_str()), ("AKNR", "FKNR"));
+//! assert!(detailed.has_alternate() && !detailed.is_truncated());
+//! assert_eq!(detailed.heuristics, Heuristics::SLAVO_GERMANIC);
+//!
+//! let detailed = encoder.encode_detailed("Constantinopolitan");
+//! assert_eq!(detailed.primary, "KNSTNTNP");
+//! assert_eq!(detailed.full_primary, "KNSTNTNPLTN");
+//! assert!(detailed.primary_truncated());
+//! ```
+
+use core::fmt;
+use core::ops::{BitOr, BitOrAssign};
+
+use crate::{METAPH_MAX_LENGTH, Metaphone3, String};
+
+/// A set of the built-in rules' heuristics about a word's origin, named after
+/// the rules of the reference implementation. See
+/// [`DetailedEncoding::heuristics`].
+///
+/// [`SLAVO_GERMANIC`](Self::SLAVO_GERMANIC) describes the word itself. The
+/// other flags are only set when a rule applied them to some letter.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Heuristics(u8);
+
+impl Heuristics {
+    /// No heuristics.
+    pub const NONE: Heuristics = Heuristics(0);
+    /// The word looks Slavic or Germanic: it starts with "J", "W", "SCH" or
+    /// "SW". Set for every such word, whether or not a rule encoded a letter
+    /// differently because of it.
+    pub const SLAVO_GERMANIC: Heuristics = Heuristics(1);
+    /// A vowel and "L" were swapped to encode a "-le" ending, as in "able".
+    pub const AL_INVERSION: Heuristics = Heuristics(1 << 1);
+    /// A Germanic spelling: "CH" as "K" ("Bach", "Brecht", "Christopher") or
+    /// an initial "J" as a vowel ("Johann").
+    pub const GERMANIC: Heuristics = Heuristics(1 << 2);
+    /// A Greek "CH" encoded as "K" ("chorus", "Christ").
+    pub const GREEK: Heuristics = Heuristics(1 << 3);
+    /// A Spanish "J" encoded as "H" ("José", "Jorge").
+    pub const SPANISH: Heuristics = Heuristics(1 << 4);
+    /// A French ending with a silent "L" ("Renault", "Breteuil").
+    pub const FRENCH: Heuristics = Heuristics(1 << 5);
+
+    const NAMES: [(Heuristics, &'static str); 6] = [
+        (Heuristics::SLAVO_GERMANIC, "SLAVO_GERMANIC"),
+        (Heuristics::AL_INVERSION, "AL_INVERSION"),
+        (Heuristics::GERMANIC, "GERMANIC"),
+        (Heuristics::GREEK, "GREEK"),
+        (Heuristics::SPANISH, "SPANISH"),
+        (Heuristics::FRENCH, "FRENCH"),
+    ];
+
+    /// Returns true if all heuristics in `other` are in `self`.
+    #[must_use]
+    pub const fn contains(self, other: Heuristics) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if the set is empty.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub(crate) fn insert(&mut self, other: Heuristics) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitOr for Heuristics {
+    type Output = Heuristics;
+
+    fn bitor(self, other: Heuristics) -> Heuristics {
+        Heuristics(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Heuristics {
+    fn bitor_assign(&mut self, other: Heuristics) {
+        self.insert(other);
+    }
+}
+
+impl fmt::Debug for Heuristics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Heuristics(")?;
+        let mut names = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name);
+        match names.next() {
+            Some(first) => f.write_str(first)?,
+            None => f.write_str("NONE")?,
+        }
+        for name in names {
+            write!(f, " | {name}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// The keys of a word and how they were made. Created by
+/// [`Metaphone3::encode_detailed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetailedEncoding {
+    /// The primary key, as returned by [`Metaphone3::encode`].
+    pub primary: String,
+    /// The secondary key, as returned by [`Metaphone3::encode`]; empty if it
+    /// equals the primary key.
+    pub secondary: String,
+    /// The primary key before truncation.
+    pub full_primary: String,
+    /// The secondary key before truncation; empty if it equals the full
+    /// primary key.
+    pub full_secondary: String,
+    /// The origin heuristics of the word. They cover every letter, including
+    /// letters whose codes fall past the [`METAPH_MAX_LENGTH`] cut and so are
+    /// only in the full-length keys.
+    pub heuristics: Heuristics,
+}
+
+impl DetailedEncoding {
+    /// Returns true if the primary key was cut to [`METAPH_MAX_LENGTH`]
+    /// characters.
+    #[must_use]
+    pub fn primary_truncated(&self) -> bool {
+        self.full_primary.len() > self.primary.len()
+    }
+
+    /// Returns true if the secondary key was cut to [`METAPH_MAX_LENGTH`]
+    /// characters. The cut key may then equal the primary key, in which case
+    /// [`secondary`](Self::secondary) is empty.
+    #[must_use]
+    pub fn secondary_truncated(&self) -> bool {
+        self.full_secondary.len() > self.secondary.len()
+    }
+
+    /// Returns true if either key was truncated.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.primary_truncated() || self.secondary_truncated()
+    }
+
+    /// Returns true if the word has a secondary key, that is an alternate
+    /// pronunciation.
+    #[must_use]
+    pub fn has_alternate(&self) -> bool {
+        !self.secondary.is_empty()
+    }
+}
+
+impl Metaphone3 {
+    /// Encodes a word like [`encode`](Self::encode) and reports the
+    /// full-length keys and the heuristics used. See the
+    /// [`detailed`](crate::detailed) module.
+    pub fn encode_detailed(&mut self, word: &str) -> DetailedEncoding {
+        // Keep encoding past the point where both keys are full.
+        self.max_length = usize::MAX;
+        let (primary, secondary) = self.encode(word);
+        self.max_length = METAPH_MAX_LENGTH;
+
+        // Empty input returns before the output buffers are reset.
+        let (full_primary, mut full_secondary): (String, String) = if self.in_buf.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (
+                self.prim_buf.iter().copied().collect(),
+                self.second_buf.iter().copied().collect(),
+            )
+        };
+        if full_secondary == full_primary {
+            full_secondary = String::new();
+        }
+        // The rules only ask whether a word looks Slavo-Germanic for some
+        // letters; report it for every word that does.
+        if self.looks_slavo_germanic() {
+            self.heuristics.insert(Heuristics::SLAVO_GERMANIC);
+        }
+        DetailedEncoding {
+            primary,
+            secondary,
+            full_primary,
+            full_secondary,
+            heuristics: self.heuristics,
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! A persistent key → ids index that can be used straight from disk.
+//!
+//! [`DiskIndexBuilder`] collects `(id, name)` pairs, encodes the names and
+//! writes a compact file: a table of the distinct [`PackedKey`]s in sorted
+//! order, each pointing into a list of sorted ids. [`DiskIndex`] reads that
+//! format from a byte slice without deserializing it: opening is O(1) and a
+//! lookup is a binary search over the key table, so an index file that is
+//! memory-mapped (see [`MappedIndex`], behind the `mmap` feature) is usable
+//! immediately, however large it is.
+//!
+//! The header records the [`Fingerprint`] of the encoder the index was built
+//! with. Opening an index with an encoder that has a different fingerprint (a
+//! different configuration, exception dictionary, rule set version or
+//! algorithm version) fails with [`DiskIndexError::Stale`] instead of silently
+//! returning wrong matches.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::disk::{DiskIndex, DiskIndexBuilder};
+//!
+//! let mut builder = DiskIndexBuilder::new(Metaphone3::new());
+//! builder.insert(10, "Smith");
+//! builder.insert(11, "Schmidt");
+//! builder.insert(12, "Jones");
+//! let bytes = builder.to_bytes();
+//!
+//! let mut encoder = Metaphone3::new();
+//! let index = DiskIndex::from_bytes(&bytes, &encoder)?;
+//! assert_eq!(index.lookup(&mut encoder, "Smyth"), [10, 11]);
+//! # Ok::<(), metaphone3::disk::DiskIndexError>(())
+//! ```
+//!
+//! # File format
+//!
+//! All integers are little-endian.
+//!
+//! | Offset | Size | Contents |
+//! |--------|------|----------|
+//! | 0 | 8 | magic `MPH3IDX\0` |
+//! | 8 | 4 | format version, currently 1 |
+//! | 12 | 4 | reserved, 0 |
+//! | 16 | 8 | encoder [`Fingerprint`] |
+//! | 24 | 8 | number of keys *K* |
+//! | 32 | 8 | number of ids *N* |
+//! | 40 | 16 × *K* | keys: packed key, index of its first id |
+//! | 40 + 16*K* | 8 × *N* | ids, grouped by key and sorted within a group |
+//!
+//! A key's ids run up to the next key's first id, or to *N* for the last key.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use crate::Metaphone3;
+use crate::fingerprint::Fingerprint;
+use crate::packed::PackedKey;
+
+const MAGIC: &[u8; 8] = b"MPH3IDX\0";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 40;
+const KEY_ENTRY_LEN: usize = 16;
+const ID_LEN: usize = 8;
+
+/// Builds a [`DiskIndex`] file. See the [module documentation](self).
+pub struct DiskIndexBuilder {
+    encoder: Metaphone3,
+    entries: Vec<(PackedKey, u64)>,
+}
+
+impl DiskIndexBuilder {
+    /// Creates an empty builder that encodes names with `encoder`.
+    #[must_use]
+    pub fn new(encoder: Metaphone3) -> Self {
+        DiskIndexBuilder {
+            encoder,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Indexes `name` under `id`, by both its primary and secondary key.
+    /// Names without keys are skipped.
+    pub fn insert(&mut self, id: u64, name: &str) {
+        let (primary, secondary) = self.encoder.encode_packed(name);
+        for key in [primary, secondary] {
+            if !key.is_empty() {
+                self.entries.push((key, id));
+            }
+        }
+    }
+
+    /// Writes the index file to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `out`.
+    pub fn write<W: Write>(&mut self, mut out: W) -> io::Result<()> {
+        self.entries.sort_unstable();
+        self.entries.dedup();
+
+        let mut starts: Vec<(PackedKey, u64)> = Vec::new();
+        for (n, &(key, _)) in self.entries.iter().enumerate() {
+            if starts.last().is_none_or(|&(last, _)| last != key) {
+                starts.push((key, n as u64));
+            }
+        }
+
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&self.encoder.fingerprint().to_u64().to_le_bytes())?;
+        out.write_all(&(starts.len() as u64).to_le_bytes())?;
+        out.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (key, start) in starts {
+            out.write_all(&key.to_u64().to_le_bytes())?;
+            out.write_all(&start.to_le_bytes())?;
+        }
+        for &(_, id) in &self.entries {
+            out.write_all(&id.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    /// Returns the index file as bytes.
+    #[must_use]
+    pub fn to_bytes(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Writing to a Vec cannot fail.
+        let _ = self.write(&mut bytes);
+        bytes
+    }
+
+    /// Writes the index file to `path`.
+    ///
+    /// The file is written next to `path` under a fresh temporary name, synced
+    /// and then renamed over `path`, so a [`MappedIndex`] of the old file keeps
+    /// seeing the old contents. Concurrent saves to the same path each write
+    /// their own temporary file; the last rename wins.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error creating, writing or renaming the file.
+    pub fn save(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        // Distinguishes the temporary files of saves within this process.
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+
+        let path = path.as_ref();
+        let (temp, file) = loop {
+            let mut temp = path.as_os_str().to_owned();
+            let save = SAVES.fetch_add(1, AtomicOrdering::Relaxed);
+            temp.push(format!(".{}.{save}.tmp", std::process::id()));
+            let temp = PathBuf::from(temp);
+            // Never reuse a file, such as one left by a crashed process that
+            // had the same id.
+            match OpenOptions::new().write(true).create_new(true).open(&temp) {
+                Ok(file) => break (temp, file),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+        };
+
+        let result = self.save_to(file).and_then(|()| fs::rename(&temp, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    fn save_to(&mut self, file: File) -> io::Result<()> {
+        let mut out = BufWriter::new(file);
+        self.write(&mut out)?;
+        out.into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()
+    }
+}
+
+/// A read-only view of an index file. See the [module documentation](self).
+#[derive(Debug, Clone, Copy)]
+pub struct DiskIndex<'a> {
+    fingerprint: Fingerprint,
+    keys: &'a [u8],
+    ids: &'a [u8],
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().expect("8 bytes"))
+}
+
+impl<'a> DiskIndex<'a> {
+    /// Opens an index file held in `bytes` for use with `encoder`.
+    ///
+    /// Only the header is checked, so this is O(1). A file that is corrupt
+    /// beyond the header yields wrong lookups, but never a panic.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiskIndexError::Stale`] if the index was built with an
+    /// encoder with a different [`Fingerprint`], and other [`DiskIndexError`]s if
+    /// `bytes` is not an index file of a supported version.
+    pub fn from_bytes(bytes: &'a [u8], encoder: &Metaphone3) -> Result<Self, DiskIndexError> {
+        let index = Self::layout(bytes)?;
+        if index.fingerprint != encoder.fingerprint() {
+            return Err(DiskIndexError::Stale);
+        }
+        Ok(index)
+    }
+
+    /// Checks the header of `bytes`, except for the fingerprint, and splits
+    /// off the key table and the ids.
+    fn layout(bytes: &'a [u8]) -> Result<Self, DiskIndexError> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(DiskIndexError::NotAnIndex);
+        }
+        let version = read_u32(bytes, 8);
+        if version != FORMAT_VERSION {
+            return Err(DiskIndexError::UnsupportedVersion(version));
+        }
+        let body = &bytes[HEADER_LEN..];
+        let key_len = usize::try_from(read_u64(bytes, 24))
+            .ok()
+            .and_then(|n| n.checked_mul(KEY_ENTRY_LEN));
+        let id_len = usize::try_from(read_u64(bytes, 32))
+            .ok()
+            .and_then(|n| n.checked_mul(ID_LEN));
+        match (key_len, id_len) {
+            (Some(key_len), Some(id_len)) if key_len.checked_add(id_len) == Some(body.len()) => {
+                let (keys, ids) = body.split_at(key_len);
+                Ok(DiskIndex {
+                    fingerprint: Fingerprint::from_u64(read_u64(bytes, 16)),
+                    keys,
+                    ids,
+                })
+            }
+            _ => Err(DiskIndexError::Truncated),
+        }
+    }
+
+    /// The fingerprint of the encoder the index was built with.
+    #[must_use]
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// Number of distinct keys.
+    #[must_use]
+    pub fn key_count(&self) -> usize {
+        self.keys.len() / KEY_ENTRY_LEN
+    }
+
+    /// Number of `(key, id)` entries.
+    #[must_use]
+    pub fn id_count(&self) -> usize {
+        self.ids.len() / ID_LEN
+    }
+
+    /// Iterates over the distinct keys in ascending order.
+    pub fn keys(&self) -> impl Iterator<Item = PackedKey> + 'a {
+        let keys = self.keys;
+        (0..self.key_count())
+            .filter_map(move |n| PackedKey::from_u64(read_u64(keys, n * KEY_ENTRY_LEN)))
+    }
+
+    /// The ids indexed under `key`, in ascending order.
+    #[must_use]
+    pub fn get(&self, key: PackedKey) -> Ids<'a> {
+        let (mut lo, mut hi) = (0, self.key_count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let found = read_u64(self.keys, mid * KEY_ENTRY_LEN);
+            match found.cmp(&key.to_u64()) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return self.ids_of(mid),
+            }
+        }
+        Ids { ids: &[] }
+    }
+
+    /// The ids of every name sharing a primary or secondary key with `name`,
+    /// deduplicated and in ascending order.
+    pub fn lookup(&self, encoder: &mut Metaphone3, name: &str) -> Vec<u64> {
+        let (primary, secondary) = encoder.encode_packed(name);
+        let mut ids: Vec<u64> = self.get(primary).collect();
+        if !secondary.is_empty() {
+            ids.extend(self.get(secondary));
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        ids
+    }
+
+    fn ids_of(&self, n: usize) -> Ids<'a> {
+        let start = read_u64(self.keys, n * KEY_ENTRY_LEN + 8);
+        let end = if n + 1 < self.key_count() {
+            read_u64(self.keys, (n + 1) * KEY_ENTRY_LEN + 8)
+        } else {
+            self.id_count() as u64
+        };
+        let byte_offset = |n: u64| usize::try_from(n).ok()?.checked_mul(ID_LEN);
+        let range = byte_offset(start)
+            .zip(byte_offset(end))
+            .and_then(|(start, end)| self.ids.get(start..end));
+        Ids {
+            ids: range.unwrap_or(&[]),
+        }
+    }
+}
+
+/// The ids of one key. Returned by [`DiskIndex::get`].
+#[derive(Debug, Clone)]
+pub struct Ids<'a> {
+    ids: &'a [u8],
+}
+
+impl Iterator for Ids<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let (id, rest) = self.ids.split_first_chunk::<ID_LEN>()?;
+        self.ids = rest;
+        Some(u64::from_le_bytes(*id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ids.len() / ID_LEN;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Ids<'_> {}
+
+/// An index file mapped into memory.
+#[cfg(feature = "mmap")]
+pub struct MappedIndex {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedIndex {
+    /// Maps the index file at `path` for use with `encoder`.
+    ///
+    /// The file must not be truncated or modified in place while it is
+    /// mapped, or lookups may read torn data or crash the process with
+    /// `SIGBUS`. [`DiskIndexBuilder::save`] replaces files by renaming, which
+    /// is safe; other writers have to do the same.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiskIndexError::Io`] if the file cannot be mapped, and the
+    /// errors of [`DiskIndex::from_bytes`].
+    pub fn open(path: impl AsRef<Path>, encoder: &Metaphone3) -> Result<Self, DiskIndexError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only, and `DiskIndexBuilder::save` replaces
+        // index files by renaming instead of rewriting them in place. Other
+        // writers are required to do the same, see above.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        DiskIndex::from_bytes(&map, encoder)?;
+        Ok(MappedIndex { map })
+    }
+
+    /// Returns the index view of the mapped file.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // the header was checked by `open`
+    pub fn index(&self) -> DiskIndex<'_> {
+        DiskIndex::layout(&self.map).expect("checked by open")
+    }
+}
+
+/// An error opening a [`DiskIndex`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DiskIndexError {
+    /// The index file could not be read.
+    Io(io::Error),
+    /// The data does not start with an index header.
+    NotAnIndex,
+    /// The index was written in an unsupported format version.
+    UnsupportedVersion(u32),
+    /// The index was built with an encoder with a different fingerprint.
+    Stale,
+    /// The data is shorter or longer than the header says.
+    Truncated,
+}
+
+impl fmt::Display for DiskIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskIndexError::Io(err) => write!(f, "I/O error: {err}"),
+            DiskIndexError::NotAnIndex => f.write_str("not a phonetic index file"),
+            DiskIndexError::UnsupportedVersion(version) => {
+                write!(f, "unsupported index format version {version}")
+            }
+            DiskIndexError::Stale => {
+                f.write_str("index was built with a different encoder fingerprint")
+            }
+            DiskIndexError::Truncated => f.write_str("index file has the wrong length"),
+        }
+    }
+}
+
+impl std::error::Error for DiskIndexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DiskIndexError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DiskIndexError {
+    fn from(err: io::Error) -> Self {
+        DiskIndexError::Io(err)
+    }
+}
@@ -0,0 +1,360 @@
+//! User-supplied exceptions that override the built-in rules.
+//!
+//! The encoder hard-wires a number of irregular words (e.g. *colonel*,
+//! *Christmas*, *sugar*). An [`ExceptionDictionary`] lets callers add their own:
+//! brand names, local surnames, or anything else the rules get wrong. It is
+//! consulted before the rule engine runs and maps
+//!
+//! - a whole word (`NIKE`) to fixed keys,
+//! - a prefix (`MC-`) to fixed keys for the prefix, or
+//! - a suffix (`-WICZ`) to fixed keys for the suffix.
+//!
+//! For prefix and suffix entries the remaining letters are encoded by the
+//! built-in rules as usual, which still see the whole word as context.
+//!
+//! Patterns are matched case-insensitively. When several patterns match, a
+//! whole-word entry wins; otherwise the longest prefix and the longest suffix
+//! that do not overlap are both applied. Keys can be given for all modes at
+//! once or per [`Mode`]; an entry without keys for the encoder's mode is
+//! ignored.
+//!
+//! # File format
+//!
+//! One entry per line, comma-separated. Blank lines and lines starting with `#`
+//! are skipped. A line has either three columns (pattern and keys for every
+//! mode) or nine (pattern and keys per mode, in the column order of the
+//! [`golden`](crate::golden) corpora: `Enc`, `EncEV`, `EncE`, `EncV`). An empty
+//! secondary key means the word has no alternate.
+//!
+//! ```text
+//! # brand names
+//! Nike,NK,NKA
+//! Xfinity,EKSFNT,,EKSFANAT,,EKSFNT,,EKSFANAT,
+//! Mc-,MK,
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::exceptions::ExceptionDictionary;
+//!
+//! let exceptions = ExceptionDictionary::parse("Nike,NK,NKA\nMc-,MK,").unwrap();
+//! let mut encoder = Metaphone3::new().with_exceptions(exceptions);
+//!
+//! assert_eq!(encoder.encode("nike"), ("NK".into(), "NKA".into()));
+//! assert_eq!(encoder.encode("McArdle"), ("MKRTL".into(), "".into()));
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, BufReader};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::fingerprint::Fnv1a;
+use crate::packed::is_key_char;
+use crate::{Mode, String};
+
+type Keys = (String, String);
+
+/// Keys for each [`Mode`], in [`Mode::ALL`] order.
+type ModeKeys = [Option<Keys>; 4];
+
+/// Uppercased patterns of one kind (whole word, prefix or suffix).
+type Table = BTreeMap<Vec<char>, ModeKeys>;
+
+/// A set of whole-word, prefix and suffix exceptions.
+///
+/// Attach one to an encoder with
+/// [`Metaphone3::with_exceptions`](crate::Metaphone3::with_exceptions).
+#[derive(Debug, Clone, Default)]
+pub struct ExceptionDictionary {
+    words: Table,
+    prefixes: Table,
+    suffixes: Table,
+    longest_prefix: usize,
+    longest_suffix: usize,
+}
+
+impl ExceptionDictionary {
+    /// Creates an empty dictionary.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry with the same keys in every mode.
+    ///
+    /// `pattern` is a whole word, a prefix ending in `-` or a suffix starting
+    /// with `-`. An empty `secondary` means there is no alternate key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExceptionError::InvalidPattern`] if `pattern` has no letters
+    /// besides the dashes, or is dashed at both ends, and
+    /// [`ExceptionError::InvalidKey`] if a key contains anything but `A`–`Z`
+    /// and `0`–`9` (the alphabet of [`PackedKey`](crate::packed::PackedKey)).
+    pub fn insert(
+        &mut self,
+        pattern: &str,
+        primary: &str,
+        secondary: &str,
+    ) -> Result<(), ExceptionError> {
+        for mode in Mode::ALL {
+            self.insert_mode(pattern, mode, primary, secondary)?;
+        }
+        Ok(())
+    }
+
+    /// Adds (or replaces) the keys of an entry for a single mode.
+    ///
+    /// # Errors
+    ///
+    /// As for [`insert`](Self::insert).
+    pub fn insert_mode(
+        &mut self,
+        pattern: &str,
+        mode: Mode,
+        primary: &str,
+        secondary: &str,
+    ) -> Result<(), ExceptionError> {
+        for key in [primary, secondary] {
+            if !key.chars().all(is_key_char) {
+                return Err(ExceptionError::InvalidKey(key.to_owned()));
+            }
+        }
+        let (table, chars) = self.table_for(pattern)?;
+        let len = chars.len();
+        table.entry(chars).or_default()[mode.index()] = Some((primary.into(), secondary.into()));
+        if pattern.ends_with('-') {
+            self.longest_prefix = self.longest_prefix.max(len);
+        } else if pattern.starts_with('-') {
+            self.longest_suffix = self.longest_suffix.max(len);
+        }
+        Ok(())
+    }
+
+    /// Parses a dictionary in the [file format](self#file-format).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExceptionError::Malformed`] for a line with the wrong number
+    /// of columns, an invalid pattern or an invalid key, with the
+    /// [reason](MalformedReason).
+    pub fn parse(text: &str) -> Result<Self, ExceptionError> {
+        let mut dict = Self::new();
+        for (n, line) in text.lines().enumerate() {
+            dict.parse_line(n + 1, line)?;
+        }
+        Ok(dict)
+    }
+
+    /// Reads a dictionary in the [file format](self#file-format).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExceptionError::Io`] if reading fails, or
+    /// [`ExceptionError::Malformed`] as for [`parse`](Self::parse).
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, ExceptionError> {
+        let mut dict = Self::new();
+        for (n, line) in reader.lines().enumerate() {
+            dict.parse_line(n + 1, &line?)?;
+        }
+        Ok(dict)
+    }
+
+    /// Loads a dictionary file.
+    ///
+    /// # Errors
+    ///
+    /// As for [`from_reader`](Self::from_reader).
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ExceptionError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Number of distinct patterns.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.len() + self.prefixes.len() + self.suffixes.len()
+    }
+
+    /// Returns true if the dictionary has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keys for an exact whole-word match of the (uppercased) input.
+    pub(crate) fn word(&self, word: &[char], mode: Mode) -> Option<&Keys> {
+        self.words.get(word)?[mode.index()].as_ref()
+    }
+
+    /// Length and keys of the longest prefix entry matching `word`, leaving
+    /// at least one letter unmatched.
+    pub(crate) fn prefix(&self, word: &[char], mode: Mode) -> Option<(usize, &Keys)> {
+        let max = self.longest_prefix.min(word.len().saturating_sub(1));
+        (1..=max).rev().find_map(|len| {
+            let keys = self.prefixes.get(&word[..len])?[mode.index()].as_ref()?;
+            Some((len, keys))
+        })
+    }
+
+    /// Length and keys of the longest suffix entry matching `word`, leaving
+    /// at least one letter unmatched.
+    pub(crate) fn suffix(&self, word: &[char], mode: Mode) -> Option<(usize, &Keys)> {
+        let max = self.longest_suffix.min(word.len().saturating_sub(1));
+        (1..=max).rev().find_map(|len| {
+            let keys = self.suffixes.get(&word[word.len() - len..])?[mode.index()].as_ref()?;
+            Some((len, keys))
+        })
+    }
+
+    /// Feeds every entry to `hash`, for [`Metaphone3::fingerprint`](crate::Metaphone3::fingerprint).
+    pub(crate) fn hash_into(&self, hash: &mut Fnv1a) {
+        for table in [&self.words, &self.prefixes, &self.suffixes] {
+            hash.write_u64(table.len() as u64);
+            for (pattern, keys) in table {
+                hash.write_str(&pattern.iter().collect::<alloc::string::String>());
+                for keys in keys {
+                    match keys {
+                        Some((primary, secondary)) => {
+                            hash.write(&[1]);
+                            hash.write_str(primary);
+                            hash.write_str(secondary);
+                        }
+                        None => hash.write(&[0]),
+                    }
+                }
+            }
+        }
+    }
+
+    fn table_for(&mut self, pattern: &str) -> Result<(&mut Table, Vec<char>), ExceptionError> {
+        let invalid = || ExceptionError::InvalidPattern(pattern.to_owned());
+        let (table, body) = match (pattern.strip_prefix('-'), pattern.strip_suffix('-')) {
+            (Some(_), Some(_)) => return Err(invalid()),
+            (None, Some(body)) => (&mut self.prefixes, body),
+            (Some(body), None) => (&mut self.suffixes, body),
+            (None, None) => (&mut self.words, pattern),
+        };
+        let chars: Vec<char> = body.chars().flat_map(char::to_uppercase).collect();
+        if chars.is_empty() {
+            return Err(invalid());
+        }
+        Ok((table, chars))
+    }
+
+    fn parse_line(&mut self, line: usize, text: &str) -> Result<(), ExceptionError> {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            return Ok(());
+        }
+        let cols: Vec<&str> = text.split(',').map(str::trim).collect();
+        let malformed = |err| match err {
+            ExceptionError::InvalidPattern(pattern) => ExceptionError::Malformed {
+                line,
+                reason: MalformedReason::InvalidPattern(pattern),
+            },
+            ExceptionError::InvalidKey(key) => ExceptionError::Malformed {
+                line,
+                reason: MalformedReason::InvalidKey(key),
+            },
+            err => err,
+        };
+        match cols.as_slice() {
+            [pattern, primary, secondary] => {
+                self.insert(pattern, primary, secondary).map_err(malformed)
+            }
+            [pattern, keys @ ..] if keys.len() == 8 => {
+                for (mode, pair) in Mode::ALL.into_iter().zip(keys.chunks(2)) {
+                    self.insert_mode(pattern, mode, pair[0], pair[1])
+                        .map_err(malformed)?;
+                }
+                Ok(())
+            }
+            _ => Err(ExceptionError::Malformed {
+                line,
+                reason: MalformedReason::Columns(cols.len()),
+            }),
+        }
+    }
+}
+
+/// An error building an [`ExceptionDictionary`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExceptionError {
+    /// The dictionary file could not be read.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// A line of a dictionary file could not be parsed.
+    Malformed {
+        /// The 1-based line number.
+        line: usize,
+        /// What was wrong with the line.
+        reason: MalformedReason,
+    },
+    /// A pattern was empty or dashed at both ends.
+    InvalidPattern(alloc::string::String),
+    /// A key contained characters other than `A`–`Z` and `0`–`9`.
+    InvalidKey(alloc::string::String),
+}
+
+impl fmt::Display for ExceptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            ExceptionError::Io(err) => write!(f, "I/O error: {err}"),
+            ExceptionError::Malformed { line, reason } => write!(f, "line {line}: {reason}"),
+            ExceptionError::InvalidPattern(pattern) => write!(f, "invalid pattern {pattern:?}"),
+            ExceptionError::InvalidKey(key) => write!(f, "invalid key {key:?}"),
+        }
+    }
+}
+
+/// Why a line of a dictionary file is [malformed](ExceptionError::Malformed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MalformedReason {
+    /// The line had this many columns instead of 3 or 9.
+    Columns(usize),
+    /// The pattern was empty or dashed at both ends.
+    InvalidPattern(alloc::string::String),
+    /// A key contained characters other than `A`–`Z` and `0`–`9`.
+    InvalidKey(alloc::string::String),
+}
+
+impl fmt::Display for MalformedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MalformedReason::Columns(n) => write!(f, "expected 3 or 9 columns, found {n}"),
+            MalformedReason::InvalidPattern(pattern) => write!(f, "invalid pattern {pattern:?}"),
+            MalformedReason::InvalidKey(key) => write!(f, "invalid key {key:?}"),
+        }
+    }
+}
+
+impl core::error::Error for ExceptionError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            ExceptionError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for ExceptionError {
+    fn from(err: io::Error) -> Self {
+        ExceptionError::Io(err)
+    }
+}
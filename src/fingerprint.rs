@@ -0,0 +1,139 @@
+//! Stable fingerprints of an encoder configuration.
+//!
+//! Keys are only comparable if they were produced by the same rules in the same
+//! configuration. A [`Fingerprint`] identifies that configuration: store it next
+//! to persisted keys, and re-encode them when
+//! [`Metaphone3::fingerprint`](crate::Metaphone3::fingerprint) no longer
+//! matches.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::fingerprint::Fingerprint;
+//!
+//! let stored: Fingerprint = Metaphone3::new().fingerprint().to_string().parse().unwrap();
+//! assert_eq!(stored, Metaphone3::new().fingerprint());
+//! assert_ne!(stored, Metaphone3::new().with_encode_vowels(true).fingerprint());
+//! ```
+//!
+//! # What is covered
+//!
+//! The fingerprint is a 64-bit FNV-1a hash of
+//!
+//! - [`ALGORITHM_VERSION`],
+//! - the [vowel](crate::Metaphone3::with_encode_vowels) and
+//!   [exact](crate::Metaphone3::with_encode_exact) options,
+//! - [`METAPH_MAX_LENGTH`],
+//! - the entries of the [exception dictionary](crate::exceptions), if any, and
+//! - the [version](crate::rules::RuleSet::with_version) of the
+//!   [custom rules](crate::rules) and the letters they are registered for, if
+//!   any.
+//!
+//! Custom rules are code and cannot be hashed, so changing what a rule emits
+//! keeps the fingerprint unless the rule set's version changes too.
+//!
+//! The hash input and function are fixed: the same configuration has the same
+//! fingerprint on every platform and in every release of the crate.
+//!
+//! # Versioning policy
+//!
+//! [`ALGORITHM_VERSION`] is bumped in every release in which the built-in rules
+//! produce a different key for *any* input. The golden corpora in `testdata/`
+//! record the rules' output, and a test pins a digest of those corpora to the
+//! current version. A rule change that alters the corpora therefore fails the
+//! test until both the digest and the version are updated.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{METAPH_MAX_LENGTH, Metaphone3};
+
+/// Version of the built-in rules. See the [versioning policy](self#versioning-policy).
+pub const ALGORITHM_VERSION: u32 = 1;
+
+/// A fingerprint of an encoder configuration. See the
+/// [module documentation](self).
+///
+/// Displays and parses as 16 lowercase hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Returns the fingerprint as an integer.
+    #[must_use]
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Rebuilds a fingerprint from [`to_u64`](Self::to_u64) output.
+    #[must_use]
+    pub const fn from_u64(bits: u64) -> Self {
+        Fingerprint(bits)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = core::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Fingerprint)
+    }
+}
+
+/// 64-bit FNV-1a, a simple hash whose output is fixed by its specification.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    /// Writes a length-prefixed string, so that consecutive strings cannot
+    /// run into each other.
+    pub(crate) fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Metaphone3 {
+    /// Returns the [`Fingerprint`] of this encoder's configuration.
+    #[must_use]
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut hash = Fnv1a::new();
+        hash.write(b"metaphone3");
+        hash.write_u64(u64::from(ALGORITHM_VERSION));
+        hash.write(&[u8::from(self.encode_vowels), u8::from(self.encode_exact)]);
+        hash.write_u64(METAPH_MAX_LENGTH as u64);
+        // Empty dictionaries and rule sets do not change any key.
+        if let Some(exceptions) = self.exceptions.as_ref().filter(|e| !e.is_empty()) {
+            hash.write(b"exceptions");
+            exceptions.hash_into(&mut hash);
+        }
+        if let Some(rules) = self.rules.as_ref().filter(|r| !r.is_empty()) {
+            hash.write(b"rules");
+            rules.hash_into(&mut hash);
+        }
+        Fingerprint(hash.finish())
+    }
+}
@@ -0,0 +1,313 @@
+//! Golden-file corpora for regression testing encoder output.
+//!
+//! A corpus is a plain-text file with one word per line followed by its
+//! primary and secondary keys in each of the four [`Mode`]s, comma-separated.
+//! A word that contains a comma, a double quote or a line break is quoted as in
+//! CSV (RFC 4180):
+//!
+//! ```text
+//! word,Enc,Enc-alt,EncEV,EncEV-alt,EncE,EncE-alt,EncV,EncV-alt
+//! Aaberg,APRK,,ABARG,,ABRG,,APARK,
+//! "Smith, Jr.",SM0JR,XMTJR,SMA0JR,XMATJR,SM0JR,XMTJR,SMA0JR,XMATJR
+//! ```
+//!
+//! This is the format of the `testdata/*-metaphone3.test` files, which were
+//! produced by the reference implementation. [`GoldenEncoder`] regenerates
+//! records from the current encoder and [`GoldenEncoder::check`] reports which
+//! keys changed, so rule changes can be reviewed key by key. The same format
+//! works for any list of names you want to pin down.
+//!
+//! ```
+//! use metaphone3::Mode;
+//! use metaphone3::golden::{GoldenEncoder, GoldenRecord};
+//!
+//! let record = GoldenRecord::parse("Aaberg,APRK,,ABARG,,ABRG,,APARK,").unwrap();
+//! assert_eq!(record.keys(Mode::EncV).0, "APARK");
+//!
+//! let mut encoder = GoldenEncoder::new();
+//! assert_eq!(encoder.record("Aaberg"), record);
+//! assert!(encoder.check(&[record]).is_empty());
+//! ```
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use smartstring::alias::CompactString;
+
+use crate::csv::{self, Quoted};
+use crate::{Metaphone3, Mode};
+
+/// A `(primary, secondary)` key pair as returned by [`Metaphone3::encode`].
+pub type Keys = (CompactString, CompactString);
+
+/// One line of a golden corpus: a word and its keys in every [`Mode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenRecord {
+    /// The word as it appears in the corpus (not uppercased).
+    pub word: String,
+    /// Keys per mode, in [`Mode::ALL`] order.
+    pub keys: [Keys; 4],
+}
+
+impl GoldenRecord {
+    /// Parses a single corpus record (without the trailing newline). A quoted
+    /// word may span several lines.
+    ///
+    /// Returns `None` unless the record has exactly nine comma-separated
+    /// columns, quoted as described in the [module documentation](self).
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut cols = csv::fields(line, ',');
+        let mut col = || cols.next()?.ok();
+        let word = col()?.into_owned();
+        let mut keys: [Keys; 4] = Default::default();
+        for slot in &mut keys {
+            *slot = (col()?.as_ref().into(), col()?.as_ref().into());
+        }
+        if cols.next().is_some() {
+            return None;
+        }
+        Some(GoldenRecord { word, keys })
+    }
+
+    /// Returns the keys recorded for `mode`.
+    #[must_use]
+    pub fn keys(&self, mode: Mode) -> &Keys {
+        &self.keys[mode.index()]
+    }
+}
+
+impl fmt::Display for GoldenRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Quoted(&self.word, ','))?;
+        for (primary, secondary) in &self.keys {
+            write!(f, ",{primary},{secondary}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single key that differs between a corpus and the current encoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    /// The word whose keys changed.
+    pub word: String,
+    /// The mode the keys were produced in.
+    pub mode: Mode,
+    /// The keys recorded in the corpus.
+    pub expected: Keys,
+    /// The keys the current encoder produces.
+    pub actual: Keys,
+}
+
+impl fmt::Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}]: {}/{} -> {}/{}",
+            self.word, self.mode, self.expected.0, self.expected.1, self.actual.0, self.actual.1
+        )
+    }
+}
+
+/// The result of checking a corpus against the current encoder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoldenDiff {
+    /// Number of records checked.
+    pub records: usize,
+    /// Every changed key, in corpus order.
+    pub changes: Vec<KeyChange>,
+}
+
+impl GoldenDiff {
+    /// Returns true if the encoder reproduced every record.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of records whose keys changed in `mode`.
+    #[must_use]
+    pub fn changed(&self, mode: Mode) -> usize {
+        self.changes.iter().filter(|c| c.mode == mode).count()
+    }
+
+    /// Percentage of records whose keys changed in `mode`.
+    #[must_use]
+    pub fn error_percent(&self, mode: Mode) -> f64 {
+        if self.records == 0 {
+            return 0.0;
+        }
+        // Corpora are far below 2^52 lines, so the conversions are exact.
+        #[allow(clippy::cast_precision_loss)]
+        let percent = self.changed(mode) as f64 / self.records as f64 * 100.0;
+        percent
+    }
+}
+
+impl fmt::Display for GoldenDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        for mode in Mode::ALL {
+            writeln!(
+                f,
+                "Encoder {mode}, changed: {} of {} ({:.2}%)",
+                self.changed(mode),
+                self.records,
+                self.error_percent(mode)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An error reading a golden corpus.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GoldenError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A record did not have nine comma-separated columns.
+    Malformed {
+        /// The 1-based line number the record starts on.
+        line: usize,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Io(err) => write!(f, "I/O error: {err}"),
+            GoldenError::Malformed { line } => {
+                write!(f, "line {line}: expected 9 comma-separated columns")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GoldenError::Io(err) => Some(err),
+            GoldenError::Malformed { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(err: io::Error) -> Self {
+        GoldenError::Io(err)
+    }
+}
+
+/// Reads every record of a corpus. Blank lines between records are skipped.
+///
+/// # Errors
+///
+/// Returns [`GoldenError::Io`] if reading fails and
+/// [`GoldenError::Malformed`] for a record that is not valid, including a
+/// quoted word that is never closed.
+pub fn read_corpus<R: BufRead>(reader: R) -> Result<Vec<GoldenRecord>, GoldenError> {
+    let mut records = Vec::new();
+    // A record, and the line it starts on, while a quoted word runs on.
+    let mut record = String::new();
+    let mut start = 0;
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if record.is_empty() {
+            if line.is_empty() {
+                continue;
+            }
+            start = n + 1;
+        } else {
+            record.push('\n');
+        }
+        record.push_str(&line);
+        if csv::is_open(&record) {
+            continue;
+        }
+        let parsed = GoldenRecord::parse(&record).ok_or(GoldenError::Malformed { line: start })?;
+        records.push(parsed);
+        record.clear();
+    }
+    if !record.is_empty() {
+        return Err(GoldenError::Malformed { line: start });
+    }
+    Ok(records)
+}
+
+/// Produces [`GoldenRecord`]s from the current encoder, with one
+/// [`Metaphone3::encode`] call per mode.
+pub struct GoldenEncoder {
+    /// One encoder per mode, in [`Mode::ALL`] order.
+    encoders: [Metaphone3; 4],
+}
+
+impl GoldenEncoder {
+    /// Creates an encoder for all four modes.
+    #[must_use]
+    pub fn new() -> Self {
+        GoldenEncoder {
+            encoders: Mode::ALL.map(|mode| Metaphone3::new().with_mode(mode)),
+        }
+    }
+
+    /// Encodes `word` in every mode.
+    pub fn record(&mut self, word: &str) -> GoldenRecord {
+        GoldenRecord {
+            word: word.to_owned(),
+            keys: self.encode(word),
+        }
+    }
+
+    /// Compares each record against the current encoder.
+    pub fn check(&mut self, records: &[GoldenRecord]) -> GoldenDiff {
+        let mut diff = GoldenDiff {
+            records: records.len(),
+            changes: Vec::new(),
+        };
+        for record in records {
+            let actual = self.encode(&record.word);
+            for (i, mode) in Mode::ALL.into_iter().enumerate() {
+                if actual[i] != record.keys[i] {
+                    diff.changes.push(KeyChange {
+                        word: record.word.clone(),
+                        mode,
+                        expected: record.keys[i].clone(),
+                        actual: actual[i].clone(),
+                    });
+                }
+            }
+        }
+        diff
+    }
+
+    fn encode(&mut self, word: &str) -> [Keys; 4] {
+        self.encoders.each_mut().map(|encoder| encoder.encode(word))
+    }
+
+    /// Writes a corpus record for every word, terminated by `\n`. Words are
+    /// quoted where needed, so [`read_corpus`] reads them back unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the writer.
+    pub fn write_corpus<'a, W, I>(&mut self, mut out: W, words: I) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = &'a str>,
+    {
+        for word in words {
+            writeln!(out, "{}", self.record(word))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for GoldenEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
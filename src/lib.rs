@@ -21,9 +21,20 @@
 //! optional [vowel](Metaphone3::with_encode_vowels) and
 //! [exact](Metaphone3::with_encode_exact) encoding modes via a builder-style API.
 //!
-//! [`Metaphone3`] is **not** thread-safe; use one encoder per thread.
+//! [`Metaphone3`] encodes through `&mut self`; use one encoder per thread, or
+//! share a [`Metaphone3Config`], which encodes through `&self`.
+//!
+//! # Cargo features
+//!
+//! - `std` (default): the modules that need I/O or hash maps ([`golden`],
+//!   [`stream`], [`cache`], [`cluster`], [`matching`], [`suggest`], [`disk`])
+//!   and file loading of [exception dictionaries](exceptions). Without it the
+//!   crate is `no_std` and only needs `alloc`.
+//! - `mmap`: memory-mapped index files, see [`disk`].
+//! - `arrow`: encoding Apache Arrow string arrays, see `arrow`.
 
 // Rust port of the Metaphone3 algorithm.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(clippy::pedantic)]
 // These pedantic lints are intentionally allowed: the algorithm is a faithful
 // port whose index arithmetic relies on `usize`/`isize` casts that are correct
@@ -39,13 +50,125 @@
     clippy::too_many_lines
 )]
 
+extern crate alloc;
+
+pub mod align;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod blocking;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod cluster;
+mod config;
+#[cfg(feature = "std")]
+mod csv;
+pub mod detailed;
+#[cfg(feature = "std")]
+pub mod disk;
+pub mod exceptions;
+pub mod fingerprint;
+#[cfg(feature = "std")]
+pub mod golden;
+#[cfg(feature = "std")]
+pub mod matching;
+pub mod packed;
+pub mod rules;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod suggest;
 #[cfg(test)]
 mod tests;
+pub mod validate;
+
+/// Maximum length, in characters, of a primary or secondary key.
+pub const METAPH_MAX_LENGTH: usize = 8;
 
-const METAPH_MAX_LENGTH: usize = 8;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
 
 use smartstring::alias::CompactString as String;
 
+pub use crate::config::Metaphone3Config;
+use crate::detailed::Heuristics;
+use crate::exceptions::ExceptionDictionary;
+use crate::rules::RuleSet;
+
+/// One of the four vowel/exact combinations an encoder can run in.
+///
+/// The variant names follow the columns of the bundled test corpora (see
+/// [`golden`]): `V` stands for [vowel](Metaphone3::with_encode_vowels) encoding
+/// and `E` for [exact](Metaphone3::with_encode_exact) encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Mode {
+    /// Neither vowels nor exact encoding (the default).
+    Enc,
+    /// Vowel encoding only.
+    EncV,
+    /// Exact encoding only.
+    EncE,
+    /// Both vowel and exact encoding.
+    EncEV,
+}
+
+impl Mode {
+    /// All four modes, in the column order of the corpus format.
+    pub const ALL: [Mode; 4] = [Mode::Enc, Mode::EncEV, Mode::EncE, Mode::EncV];
+
+    /// Returns the mode matching the given option values.
+    #[must_use]
+    pub fn from_options(encode_vowels: bool, encode_exact: bool) -> Self {
+        match (encode_vowels, encode_exact) {
+            (false, false) => Mode::Enc,
+            (true, false) => Mode::EncV,
+            (false, true) => Mode::EncE,
+            (true, true) => Mode::EncEV,
+        }
+    }
+
+    /// Whether this mode encodes vowels.
+    #[must_use]
+    pub fn encode_vowels(self) -> bool {
+        matches!(self, Mode::EncV | Mode::EncEV)
+    }
+
+    /// Whether this mode uses exact encoding.
+    #[must_use]
+    pub fn encode_exact(self) -> bool {
+        matches!(self, Mode::EncE | Mode::EncEV)
+    }
+
+    /// Position of this mode in [`Mode::ALL`], for tables indexed by mode.
+    #[must_use]
+    pub fn index(self) -> usize {
+        match self {
+            Mode::Enc => 0,
+            Mode::EncEV => 1,
+            Mode::EncE => 2,
+            Mode::EncV => 3,
+        }
+    }
+
+    /// Returns the short name used in reports, e.g. `"EncEV"`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Enc => "Enc",
+            Mode::EncV => "EncV",
+            Mode::EncE => "EncE",
+            Mode::EncEV => "EncEV",
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// A Metaphone 3 encoder.
 ///
 /// Construct one with [`Metaphone3::new`], optionally configure it with
@@ -53,16 +176,36 @@ use smartstring::alias::CompactString as String;
 /// [`with_encode_exact`](Metaphone3::with_encode_exact), then call
 /// [`encode`](Metaphone3::encode). A single instance can encode many words and
 /// reuses its internal buffers between calls.
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)] // independent options and per-word flags
 pub struct Metaphone3 {
     in_buf: Vec<char>,
     length: usize,
     idx: usize,
     last_idx: usize,
+    // End of the letters being encoded; those after it are covered by an
+    // exception suffix.
+    end: usize,
     prim_buf: Vec<char>,
     second_buf: Vec<char>,
+    // Exact-mode output, filled alongside the approximate output while
+    // encoding all modes.
+    exact_bufs: Option<(Vec<char>, Vec<char>)>,
+    // Set while `exact_bufs` or `spans` is in use, so that plain encoding
+    // checks one flag instead of both on every letter and output character.
+    side_output: bool,
     encode_vowels: bool,
     encode_exact: bool,
     flag_al_inversion: bool,
+    // Output length after which encoding stops early.
+    max_length: usize,
+    // Longest input `try_encode` accepts, in characters.
+    max_input_length: usize,
+    heuristics: Heuristics,
+    exceptions: Option<Arc<ExceptionDictionary>>,
+    rules: Option<Arc<RuleSet>>,
+    // Input spans of the output characters, only recorded while aligning.
+    spans: Option<align::Spans>,
 }
 
 impl Metaphone3 {
@@ -74,11 +217,20 @@ impl Metaphone3 {
             length: 0,
             idx: 0,
             last_idx: 0,
+            end: 0,
             prim_buf: Vec::new(),
             second_buf: Vec::new(),
+            exact_bufs: None,
+            side_output: false,
             encode_vowels: false,
             encode_exact: false,
             flag_al_inversion: false,
+            max_length: METAPH_MAX_LENGTH,
+            max_input_length: usize::MAX,
+            heuristics: Heuristics::NONE,
+            exceptions: None,
+            rules: None,
+            spans: None,
         }
     }
 
@@ -96,6 +248,38 @@ impl Metaphone3 {
         self
     }
 
+    /// Sets both the vowel and exact options from a [`Mode`].
+    #[must_use]
+    pub fn with_mode(self, mode: Mode) -> Self {
+        self.with_encode_vowels(mode.encode_vowels())
+            .with_encode_exact(mode.encode_exact())
+    }
+
+    /// Sets a dictionary of words, prefixes and suffixes whose keys override
+    /// the built-in rules. See the [`exceptions`] module.
+    ///
+    /// The dictionary is shared, so one can be loaded once and handed to any
+    /// number of encoders.
+    #[must_use]
+    pub fn with_exceptions(mut self, exceptions: impl Into<Arc<ExceptionDictionary>>) -> Self {
+        self.exceptions = Some(exceptions.into());
+        self
+    }
+
+    /// Sets custom spelling rules that run alongside the built-in ones. See the
+    /// [`rules`] module.
+    #[must_use]
+    pub fn with_rules(mut self, rules: impl Into<Arc<RuleSet>>) -> Self {
+        self.rules = Some(rules.into());
+        self
+    }
+
+    /// Returns the [`Mode`] this encoder is configured for.
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        Mode::from_options(self.encode_vowels, self.encode_exact)
+    }
+
     /// Encodes a word into its primary and secondary Metaphone 3 keys.
     ///
     /// Returns a `(primary, secondary)` tuple. The primary key is always present
@@ -113,19 +297,121 @@ impl Metaphone3 {
     /// assert_eq!(secondary, "AXN");
     /// ```
     pub fn encode(&mut self, word: &str) -> (String, String) {
-        if word.is_empty() {
+        self.encode_chars(word.chars())
+    }
+
+    /// Encodes a word given as ISO-8859-1 (Latin-1) bytes.
+    ///
+    /// Each byte is the Unicode code point of the same value, so no transcoding
+    /// to UTF-8 is needed. The result is identical to [`encode`](Self::encode)
+    /// on the equivalent string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use metaphone3::Metaphone3;
+    /// let mut encoder = Metaphone3::new();
+    /// // "Muñoz" in Latin-1
+    /// assert_eq!(encoder.encode_latin1(b"Mu\xF1oz"), encoder.encode("Muñoz"));
+    /// ```
+    pub fn encode_latin1(&mut self, word: &[u8]) -> (String, String) {
+        self.encode_chars(word.iter().copied().map(char::from))
+    }
+
+    /// Encodes a word given as a sequence of characters, for input that is not
+    /// stored as a `&str`. The result is identical to [`encode`](Self::encode)
+    /// on the collected string.
+    pub fn encode_chars<I>(&mut self, word: I) -> (String, String)
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.load(word);
+        if self.in_buf.is_empty() {
             return (String::new(), String::new());
         }
+        self.encode_loaded()
+    }
 
-        // Reset state
-        self.flag_al_inversion = false;
+    /// Encodes a word in all four [`Mode`]s at once, regardless of this
+    /// encoder's own vowel and exact options.
+    ///
+    /// Returns the keys in the order of [`Mode::ALL`], each identical to what
+    /// [`encode`](Self::encode) returns in that mode. The word is uppercased
+    /// once, and the exact and approximate keys come out of the same pass
+    /// over it.
+    ///
+    /// This is not a single walk: it takes two passes, one without and one
+    /// with vowels. Vowel encoding changes how far many rules advance through
+    /// the word (a `-LE` ending, a silent `UE` or a run of vowels is consumed
+    /// differently), so the two modes reach different letters at each step
+    /// and cannot share a cursor. With custom [rules] or an
+    /// [exception dictionary](exceptions) the modes are encoded one at a time,
+    /// in four passes, since both may depend on the mode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use metaphone3::{Metaphone3, Mode};
+    /// let mut encoder = Metaphone3::new();
+    /// let keys = encoder.encode_all_modes("Smith");
+    /// for (mode, keys) in Mode::ALL.into_iter().zip(keys) {
+    ///     assert_eq!(keys, Metaphone3::new().with_mode(mode).encode("Smith"));
+    /// }
+    /// ```
+    pub fn encode_all_modes(&mut self, word: &str) -> [(String, String); 4] {
+        let options = (self.encode_vowels, self.encode_exact);
+        let mut keys: [(String, String); 4] = Default::default();
+        self.load(word.chars());
+        if self.in_buf.is_empty() {
+            return keys;
+        }
+
+        if self.rules.is_some() || self.exceptions.is_some() {
+            for (slot, mode) in keys.iter_mut().zip(Mode::ALL) {
+                (self.encode_vowels, self.encode_exact) =
+                    (mode.encode_vowels(), mode.encode_exact());
+                *slot = self.encode_loaded();
+            }
+        } else {
+            // Every exact/approximate difference goes through the
+            // `metaph_add_exact_approx*` helpers, which write the exact output
+            // to `exact_bufs` while the approximate output goes to the usual
+            // buffers.
+            self.encode_exact = false;
+            let mut exact_bufs = self.exact_bufs.take().unwrap_or_default();
+            for vowels in [false, true] {
+                self.encode_vowels = vowels;
+                exact_bufs.0.clear();
+                exact_bufs.1.clear();
+                self.exact_bufs = Some(exact_bufs);
+                self.side_output = true;
+                keys[Mode::from_options(vowels, false).index()] = self.encode_loaded();
+                self.side_output = false;
+                exact_bufs = self.exact_bufs.take().unwrap_or_default();
+                keys[Mode::from_options(vowels, true).index()] =
+                    Self::keys(&exact_bufs.0, &exact_bufs.1);
+            }
+        }
+        (self.encode_vowels, self.encode_exact) = options;
+        keys
+    }
+
+    /// Resets the per-word state and loads the uppercased word into `in_buf`.
+    fn load<I>(&mut self, word: I)
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.heuristics = Heuristics::NONE;
         // Uppercase char-by-char, reusing the existing buffer's capacity to avoid
         // allocating a temporary String plus a fresh Vec on every call.
         self.in_buf.clear();
         self.in_buf
-            .extend(word.chars().flat_map(char::to_uppercase));
-        self.length = self.in_buf.len();
-        self.last_idx = self.length - 1;
+            .extend(word.into_iter().flat_map(char::to_uppercase));
+    }
+
+    /// Encodes the non-empty word in `in_buf` with the current options.
+    fn encode_loaded(&mut self) -> (String, String) {
+        self.flag_al_inversion = false;
 
         // Prepare output buffers
         self.prim_buf.clear();
@@ -133,65 +419,161 @@ impl Metaphone3 {
         self.second_buf.clear();
         self.second_buf.reserve(METAPH_MAX_LENGTH);
 
+        if self.exceptions.is_some() {
+            return self.encode_with_exceptions();
+        }
+
+        self.encode_in_buf(0, self.in_buf.len());
+        self.finish()
+    }
+
+    /// Encodes `in_buf` after consulting the exception dictionary: a whole-word
+    /// entry replaces the rules entirely, while prefix and suffix entries
+    /// contribute fixed keys around the rule-based encoding of the letters in
+    /// between.
+    fn encode_with_exceptions(&mut self) -> (String, String) {
+        let Some(exceptions) = self.exceptions.clone() else {
+            return (String::new(), String::new());
+        };
+        let mode = self.mode();
+
+        if let Some((primary, secondary)) = exceptions.word(&self.in_buf, mode) {
+            self.push_keys(primary, secondary);
+            self.record_spans(0, self.in_buf.len());
+            return self.finish();
+        }
+
+        let head = exceptions.prefix(&self.in_buf, mode);
+        let head_len = head.map_or(0, |(len, _)| len);
+        let tail = exceptions.suffix(&self.in_buf[head_len..], mode);
+        let end = self.in_buf.len() - tail.map_or(0, |(len, _)| len);
+
+        if let Some((_, (primary, secondary))) = head {
+            self.push_keys(primary, secondary);
+            self.record_spans(0, head_len);
+        }
+        self.encode_in_buf(head_len, end);
+        if let Some((_, (primary, secondary))) = tail {
+            self.push_keys(primary, secondary);
+            self.record_spans(end, self.in_buf.len());
+        }
+        self.finish()
+    }
+
+    /// Appends fixed keys to the output buffers; an empty secondary key stands
+    /// for "same as primary".
+    fn push_keys(&mut self, primary: &str, secondary: &str) {
+        let secondary = if secondary.is_empty() {
+            primary
+        } else {
+            secondary
+        };
+        self.prim_buf.extend(primary.chars());
+        self.second_buf.extend(secondary.chars());
+    }
+
+    /// Attributes the output characters added since the last call to the
+    /// letters `in_buf[start..end]`. Only does anything while aligning, see
+    /// [`align`].
+    fn record_spans(&mut self, start: usize, end: usize) {
+        if let Some(spans) = &mut self.spans {
+            let span = (start, end.clamp(start + 1, self.in_buf.len()));
+            spans.primary.resize(self.prim_buf.len(), span);
+            spans.secondary.resize(self.second_buf.len(), span);
+        }
+    }
+
+    /// Runs the rule engine over `in_buf[start..end]`, appending to the output
+    /// buffers. The rules still see the whole of `in_buf` as context.
+    fn encode_in_buf(&mut self, start: usize, end: usize) {
+        self.length = self.in_buf.len();
+        self.last_idx = self.length - 1;
+        self.end = end;
+        let rules = self.rules.clone();
+
         // Main encoding loop - rune by rune through the input
-        self.idx = 0;
-        while self.idx < self.length {
+        self.idx = start;
+        while self.idx < end {
             // Check if buffers are full
-            if self.prim_buf.len() >= METAPH_MAX_LENGTH
-                && self.second_buf.len() >= METAPH_MAX_LENGTH
+            if self.prim_buf.len() >= self.max_length
+                && self.second_buf.len() >= self.max_length
+                && (!self.side_output
+                    || self.exact_bufs.as_ref().is_none_or(|(prim, second)| {
+                        prim.len() >= self.max_length && second.len() >= self.max_length
+                    }))
             {
                 break;
             }
 
             let c = self.in_buf[self.idx];
+            let letter = self.idx;
 
-            match c {
-                'B' => self.encode_b(),
-                'ß' | 'Ç' => self.metaph_add('S'),
-                'C' => self.encode_c(),
-                'D' => self.encode_d(),
-                'F' => self.encode_f(),
-                'G' => self.encode_g(),
-                'H' => self.encode_h(),
-                'J' => self.encode_j(),
-                'K' => self.encode_k(),
-                'L' => self.encode_l(),
-                'M' => self.encode_m(),
-                'N' => self.encode_n(),
-                'Ñ' => self.metaph_add('N'),
-                'P' => self.encode_p(),
-                'Q' => self.encode_q(),
-                'R' => self.encode_r(),
-                'S' => self.encode_s(),
-                'T' => self.encode_t(),
-                'Ð' | 'Þ' => self.metaph_add('0'),
-                'V' => self.encode_v(),
-                'W' => self.encode_w(),
-                'X' => self.encode_x(),
-                'Z' => self.encode_z(),
-                _ => {
-                    if Self::is_vowel_char(c) {
-                        self.encode_vowels();
+            match &rules {
+                Some(rules) => {
+                    if !rules.apply_before(c, self) {
+                        self.encode_letter(c);
+                        rules.apply_after(c, self);
                     }
                 }
+                None => self.encode_letter(c),
+            }
+            if self.side_output {
+                self.record_spans(letter, self.idx + 1);
             }
 
             // Always increment idx to match Go's for loop behavior (e.idx++)
             // This happens regardless of whether the encoder modified idx
             self.idx += 1;
         }
+    }
 
-        // Trim buffers if needed
-        if self.prim_buf.len() > METAPH_MAX_LENGTH {
-            self.prim_buf.truncate(METAPH_MAX_LENGTH);
-        }
-        if self.second_buf.len() > METAPH_MAX_LENGTH {
-            self.second_buf.truncate(METAPH_MAX_LENGTH);
+    /// Dispatches the letter at the cursor to its built-in encoding routine.
+    fn encode_letter(&mut self, c: char) {
+        match c {
+            'B' => self.encode_b(),
+            'ß' | 'Ç' => self.metaph_add('S'),
+            'C' => self.encode_c(),
+            'D' => self.encode_d(),
+            'F' => self.encode_f(),
+            'G' => self.encode_g(),
+            'H' => self.encode_h(),
+            'J' => self.encode_j(),
+            'K' => self.encode_k(),
+            'L' => self.encode_l(),
+            'M' => self.encode_m(),
+            'N' => self.encode_n(),
+            'Ñ' => self.metaph_add('N'),
+            'P' => self.encode_p(),
+            'Q' => self.encode_q(),
+            'R' => self.encode_r(),
+            'S' => self.encode_s(),
+            'T' => self.encode_t(),
+            'Ð' | 'Þ' => self.metaph_add('0'),
+            'V' => self.encode_v(),
+            'W' => self.encode_w(),
+            'X' => self.encode_x(),
+            'Z' => self.encode_z(),
+            _ => {
+                if Self::is_vowel_char(c) {
+                    self.encode_vowels();
+                }
+            }
         }
+    }
+
+    /// Converts the output buffers into the returned keys, truncated to
+    /// [`METAPH_MAX_LENGTH`]. The buffers themselves are left untruncated.
+    fn finish(&mut self) -> (String, String) {
+        Self::keys(&self.prim_buf, &self.second_buf)
+    }
 
-        // Convert to strings
-        let primary: String = self.prim_buf.iter().copied().collect();
-        let secondary: String = self.second_buf.iter().copied().collect();
+    /// Converts output buffers into truncated keys, with an empty secondary
+    /// key if it equals the primary one.
+    fn keys(prim_buf: &[char], second_buf: &[char]) -> (String, String) {
+        let truncated =
+            |buf: &[char]| -> String { buf.iter().take(METAPH_MAX_LENGTH).copied().collect() };
+        let primary = truncated(prim_buf);
+        let secondary = truncated(second_buf);
 
         if primary == secondary {
             (primary, String::new())
@@ -478,6 +860,7 @@ impl Metaphone3 {
                 self.metaph_add_alt('K', 'X');
             }
             self.idx += 1;
+            self.heuristics.insert(Heuristics::GERMANIC);
             return true;
         }
 
@@ -583,6 +966,7 @@ impl Metaphone3 {
                 self.metaph_add_alt('K', 'X');
             }
             self.idx += 1;
+            self.heuristics.insert(Heuristics::GREEK);
             return true;
         }
 
@@ -646,6 +1030,7 @@ impl Metaphone3 {
         {
             self.metaph_add_alt('K', 'X');
             self.idx += 1;
+            self.heuristics.insert(Heuristics::GREEK);
             return true;
         }
 
@@ -892,17 +1277,14 @@ impl Metaphone3 {
             return;
         }
 
-        if self.encode_exact {
-            // "final de-voicing" in this case
-            // e.g. 'missed' == 'mist'
-            if self.string_at_end(-3, &["SSED"]) {
-                self.metaph_add('T');
-            } else {
-                self.metaph_add('D');
-            }
+        // "final de-voicing" in exact mode in this case
+        // e.g. 'missed' == 'mist'
+        let exact = if self.string_at_end(-3, &["SSED"]) {
+            'T'
         } else {
-            self.metaph_add('T');
-        }
+            'D'
+        };
+        self.metaph_add_exact_approx(exact, 'T');
         // Don't increment idx - let main loop handle it
     }
 
@@ -949,16 +1331,9 @@ impl Metaphone3 {
                 self.metaph_add_exact_approx_alt("D0", "D0", "T0", "T0");
                 self.idx += 2;
             } else {
-                if self.encode_exact {
-                    // devoice it
-                    if self.string_at(0, &["DT"]) {
-                        self.metaph_add('T');
-                    } else {
-                        self.metaph_add('D');
-                    }
-                } else {
-                    self.metaph_add('T');
-                }
+                // devoice it
+                let exact = if self.string_at(0, &["DT"]) { 'T' } else { 'D' };
+                self.metaph_add_exact_approx(exact, 'T');
                 self.idx += 1;
             }
 
@@ -1812,6 +2187,7 @@ impl Metaphone3 {
                 self.metaph_add('A');
             }
             self.advance_counter(1, 0);
+            self.heuristics.insert(Heuristics::SPANISH);
             return true;
         }
 
@@ -1824,10 +2200,12 @@ impl Metaphone3 {
                     self.metaph_add_str("JRJ", "HRH");
                 }
                 self.advance_counter(4, 4);
+                self.heuristics.insert(Heuristics::SPANISH);
                 return true;
             }
             self.metaph_add_alt('J', 'H');
             self.advance_counter(1, 0);
+            self.heuristics.insert(Heuristics::SPANISH);
             return true;
         }
 
@@ -1841,6 +2219,7 @@ impl Metaphone3 {
         {
             self.metaph_add('A');
             self.advance_counter(1, 0);
+            self.heuristics.insert(Heuristics::GERMANIC);
             return true;
         }
         false
@@ -1854,6 +2233,7 @@ impl Metaphone3 {
                 self.metaph_add_str("HH", "HH");
             }
             self.advance_counter(3, 2);
+            self.heuristics.insert(Heuristics::SPANISH);
             return true;
         }
         false
@@ -1903,6 +2283,7 @@ impl Metaphone3 {
         {
             self.metaph_add('H');
             self.advance_counter(1, 0);
+            self.heuristics.insert(Heuristics::SPANISH);
             return true;
         }
         false
@@ -2309,6 +2690,7 @@ impl Metaphone3 {
                 || self.string_at(-9, &["SUMMERSAULT"]))
         {
             self.idx += 1;
+            self.heuristics.insert(Heuristics::FRENCH);
             return true;
         }
 
@@ -2318,6 +2700,7 @@ impl Metaphone3 {
     fn encode_french_euil(&mut self) -> bool {
         // e.g. "auteuil"
         if self.string_at_end(-3, &["EUIL"]) {
+            self.heuristics.insert(Heuristics::FRENCH);
             return true;
         }
         false
@@ -2327,6 +2710,7 @@ impl Metaphone3 {
         // e.g. "proulx"
         if self.string_at_end(-2, &["OULX"]) {
             self.idx += 1;
+            self.heuristics.insert(Heuristics::FRENCH);
             return true;
         }
         false
@@ -2470,6 +2854,7 @@ impl Metaphone3 {
         {
             self.metaph_add_str("AL", "AL");
             self.flag_al_inversion = true;
+            self.heuristics.insert(Heuristics::AL_INVERSION);
 
             // eat redundant 'L'
             if self.char_at(offset + 2, 'L') {
@@ -3174,6 +3559,7 @@ impl Metaphone3 {
                 {
                     self.metaph_add_str("SAL", "SAL");
                     self.flag_al_inversion = true;
+                    self.heuristics.insert(Heuristics::AL_INVERSION);
                 } else {
                     self.metaph_add_str("SL", "SL");
                 }
@@ -3903,11 +4289,8 @@ impl Metaphone3 {
         if self.string_at_end(0, &["WICZ", "WITZ"]) {
             if self.encode_vowels {
                 // don't dupe A's
-                if !self.prim_buf.is_empty() && self.prim_buf[self.prim_buf.len() - 1] == 'A' {
-                    self.metaph_add_str("TS", "FAX");
-                } else {
-                    self.metaph_add_str("ATS", "FAX");
-                }
+                self.metaph_add_str("A", "");
+                self.metaph_add_str("TS", "FAX");
             } else {
                 self.metaph_add_str("TS", "FX");
             }
@@ -3932,6 +4315,7 @@ impl Metaphone3 {
         if self.idx == 0 && self.is_vowel_at(1) {
             // Witter should match Vitter
             if self.germanic_or_slavic_name_beginning_with_w() {
+                self.heuristics.insert(Heuristics::SLAVO_GERMANIC);
                 if self.encode_vowels {
                     self.metaph_add_exact_approx_alt("A", "VA", "A", "FA");
                 } else {
@@ -5014,52 +5398,50 @@ impl Metaphone3 {
 
     /// Adds different encoding characters to primary and secondary buffers
     fn metaph_add_alt(&mut self, prim: char, second: char) {
-        // Add to primary buffer if not null (don't duplicate A's)
-        if prim != '\0'
-            && !(prim == 'A'
-                && !self.prim_buf.is_empty()
-                && self.prim_buf[self.prim_buf.len() - 1] == 'A')
-        {
-            self.prim_buf.push(prim);
+        Self::push_char(&mut self.prim_buf, prim);
+        Self::push_char(&mut self.second_buf, second);
+        if self.side_output {
+            self.exact_add_alt(prim, second);
         }
+    }
 
-        // Add to secondary buffer if not null (don't duplicate A's)
-        if second != '\0'
-            && !(second == 'A'
-                && !self.second_buf.is_empty()
-                && self.second_buf[self.second_buf.len() - 1] == 'A')
-        {
-            self.second_buf.push(second);
+    /// Mirrors [`metaph_add_alt`](Self::metaph_add_alt) into `exact_bufs`.
+    #[cold]
+    fn exact_add_alt(&mut self, prim: char, second: char) {
+        if let Some((exact_prim, exact_second)) = &mut self.exact_bufs {
+            Self::push_char(exact_prim, prim);
+            Self::push_char(exact_second, second);
         }
     }
 
     /// Adds strings to both buffers
     fn metaph_add_str(&mut self, prim: &str, second: &str) {
-        // Add primary string (don't duplicate A's)
-        if !(prim == "A"
-            && !self.prim_buf.is_empty()
-            && self.prim_buf[self.prim_buf.len() - 1] == 'A')
-        {
-            for c in prim.chars() {
-                self.prim_buf.push(c);
-            }
+        Self::push_str(&mut self.prim_buf, prim);
+        Self::push_str(&mut self.second_buf, second);
+        if self.side_output {
+            self.exact_add_str(prim, second);
         }
+    }
 
-        // Add secondary string (don't duplicate A's)
-        if !second.is_empty()
-            && !(second == "A"
-                && !self.second_buf.is_empty()
-                && self.second_buf[self.second_buf.len() - 1] == 'A')
-        {
-            for c in second.chars() {
-                self.second_buf.push(c);
-            }
+    /// Mirrors [`metaph_add_str`](Self::metaph_add_str) into `exact_bufs`.
+    #[cold]
+    fn exact_add_str(&mut self, prim: &str, second: &str) {
+        if let Some((exact_prim, exact_second)) = &mut self.exact_bufs {
+            Self::push_str(exact_prim, prim);
+            Self::push_str(exact_second, second);
         }
     }
 
     /// Adds exact or approximate encoding based on the `encode_exact` setting
     fn metaph_add_exact_approx(&mut self, exact: char, approx: char) {
-        if self.encode_exact {
+        if self.side_output
+            && let Some((exact_prim, exact_second)) = &mut self.exact_bufs
+        {
+            Self::push_char(exact_prim, exact);
+            Self::push_char(exact_second, exact);
+            Self::push_char(&mut self.prim_buf, approx);
+            Self::push_char(&mut self.second_buf, approx);
+        } else if self.encode_exact {
             self.metaph_add(exact);
         } else {
             self.metaph_add(approx);
@@ -5068,22 +5450,41 @@ impl Metaphone3 {
 
     /// String version of `metaph_add_exact_approx`
     fn metaph_add_exact_approx_str(&mut self, exact: &str, approx: &str) {
-        if self.encode_exact {
-            self.metaph_add_str(exact, exact);
-        } else {
-            self.metaph_add_str(approx, approx);
-        }
+        self.metaph_add_exact_approx_alt(exact, exact, approx, approx);
     }
 
     /// Adds exact or approximate encodings with alternates
     fn metaph_add_exact_approx_alt(&mut self, exact: &str, alt_exact: &str, main: &str, alt: &str) {
-        if self.encode_exact {
+        if self.side_output
+            && let Some((exact_prim, exact_second)) = &mut self.exact_bufs
+        {
+            Self::push_str(exact_prim, exact);
+            Self::push_str(exact_second, alt_exact);
+            Self::push_str(&mut self.prim_buf, main);
+            Self::push_str(&mut self.second_buf, alt);
+        } else if self.encode_exact {
             self.metaph_add_str(exact, alt_exact);
         } else {
             self.metaph_add_str(main, alt);
         }
     }
 
+    /// Appends `c` to an output buffer unless it is NUL or would duplicate an
+    /// 'A'.
+    fn push_char(buf: &mut Vec<char>, c: char) {
+        if c != '\0' && !(c == 'A' && buf.last() == Some(&'A')) {
+            buf.push(c);
+        }
+    }
+
+    /// Appends `s` to an output buffer unless it is an 'A' that would be
+    /// duplicated.
+    fn push_str(buf: &mut Vec<char>, s: &str) {
+        if !(s == "A" && buf.last() == Some(&'A')) {
+            buf.extend(s.chars());
+        }
+    }
+
     /// Skip vowels from the given position, returning the position after vowels
     fn skip_vowels(&self, at: usize) -> usize {
         if at >= self.length {
@@ -5143,18 +5544,21 @@ impl Metaphone3 {
         }
     }
 
-    /// Check if the word looks Slavic or Germanic
-    fn is_slavo_germanic(&self) -> bool {
-        if self.length == 0 {
-            return false;
-        }
-
-        let first = self.in_buf[0];
-        if first == 'J' || first == 'W' {
-            return true;
+    /// Check if the word looks Slavic or Germanic, recording the heuristic if so
+    fn is_slavo_germanic(&mut self) -> bool {
+        let slavo_germanic = self.looks_slavo_germanic();
+        if slavo_germanic {
+            self.heuristics.insert(Heuristics::SLAVO_GERMANIC);
         }
+        slavo_germanic
+    }
 
-        self.string_start(&["SCH", "SW"])
+    /// [`is_slavo_germanic`](Self::is_slavo_germanic) without recording the
+    /// heuristic.
+    fn looks_slavo_germanic(&self) -> bool {
+        matches!(self.in_buf.first(), Some('J' | 'W'))
+            || self.in_buf.starts_with(&['S', 'C', 'H'])
+            || self.in_buf.starts_with(&['S', 'W'])
     }
 
     /// Check if the input word is the root itself or a common inflection of the root
@@ -1,7 +1,127 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
 use metaphone3::Metaphone3;
+use metaphone3::cluster::Clusterer;
+
+const USAGE: &str = "usage: metaphone3 cluster [--column N] [--delimiter C] [--secondary] \
+                     [--vowels] [--exact] [--mapping] < input";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {
+            demo();
+            ExitCode::SUCCESS
+        }
+        Some("cluster") => match cluster(&args[1..]) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{err}\n{USAGE}");
+                ExitCode::FAILURE
+            }
+        },
+        Some(_) => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Clusters the values of stdin (one per line, or one column of a delimited
+/// file, quoted as in CSV) and prints either a report or a `value,canonical`
+/// CSV mapping.
+fn cluster(args: &[String]) -> Result<(), String> {
+    let mut column = None;
+    let mut delimiter = ',';
+    let mut encoder = Metaphone3::new();
+    let mut secondary = false;
+    let mut mapping = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--column" => {
+                let n = args.next().ok_or("--column needs a value")?;
+                column = Some(
+                    n.parse::<usize>()
+                        .map_err(|_| format!("bad column {n:?}"))?,
+                );
+            }
+            "--delimiter" => {
+                let d = args.next().ok_or("--delimiter needs a value")?;
+                let mut chars = d.chars();
+                delimiter = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("bad delimiter {d:?}")),
+                };
+            }
+            "--secondary" => secondary = true,
+            "--vowels" => encoder = encoder.with_encode_vowels(true),
+            "--exact" => encoder = encoder.with_encode_exact(true),
+            "--mapping" => mapping = true,
+            other => return Err(format!("unknown option {other:?}")),
+        }
+    }
+
+    // `encode_lines` splits and unquotes the column; the keys are unused.
+    let mut values = Vec::new();
+    let mut reader = Metaphone3::new();
+    let mut lines = reader.encode_lines(io::stdin().lock());
+    if let Some(column) = column {
+        lines = lines.with_column(column).with_delimiter(delimiter);
+    }
+    while let Some(record) = lines.next_record() {
+        let (value, _, _) = record.map_err(|err| err.to_string())?;
+        values.push(value.to_owned());
+    }
+
+    let clusters = Clusterer::new(encoder)
+        .with_secondary_keys(secondary)
+        .cluster(values.iter().map(String::as_str));
+
+    let mut out = io::stdout().lock();
+    let result = if mapping {
+        clusters.iter().try_for_each(|cluster| {
+            cluster.values.iter().try_for_each(|v| {
+                let canonical = cluster.canonical();
+                writeln!(out, "{},{}", csv_field(&v.value), csv_field(canonical))
+            })
+        })
+    } else {
+        clusters.iter().try_for_each(|cluster| {
+            writeln!(
+                out,
+                "{} ({} rows, {} values) -> {}",
+                cluster.key,
+                cluster.rows,
+                cluster.values.len(),
+                cluster.canonical()
+            )?;
+            for v in &cluster.values {
+                writeln!(out, "{:>8}  {}", v.count, v.value)?;
+            }
+            for v in &cluster.candidates {
+                writeln!(out, "{:>8}  ? {}", v.count, v.value)?;
+            }
+            writeln!(out)
+        })
+    };
+    result.map_err(|err| err.to_string())
+}
+
+/// Quotes a CSV field if it contains a comma, a double quote or a line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
 
 // --- Main function for demonstration ---
-fn main() {
+fn demo() {
     let mut encoder = Metaphone3::new();
 
     let tests = vec![
@@ -0,0 +1,291 @@
+//! Candidate pairs between two collections of names.
+//!
+//! A [`PhoneticIndex`] encodes every name of the *left* collection once and
+//! indexes it by its primary and secondary keys. Names of the *right*
+//! collection are then streamed through
+//! [`candidates`](PhoneticIndex::candidates), which looks up each name and
+//! yields the [`CandidatePair`]s whose keys collide, classified by which keys
+//! matched and optionally scored with [`jaro_winkler`].
+//!
+//! Memory is bounded by the left collection: the index stores two
+//! [`PackedKey`]s and two `u32` ids per left name, and borrows the names
+//! themselves. The right collection is consumed lazily, one name at a time, so
+//! it can be arbitrarily large (a file read line by line, say). Very common
+//! keys can be excluded with [`with_max_block`](PhoneticIndex::with_max_block)
+//! to keep the number of pairs per name bounded as well.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::matching::{MatchClass, PhoneticIndex};
+//!
+//! let left = ["Smith", "Schmidt", "Jones"];
+//! let index = PhoneticIndex::new(Metaphone3::new(), &left).with_scoring(true);
+//!
+//! let pairs: Vec<_> = index.candidates(["Smyth"]).collect();
+//! assert_eq!(pairs.len(), 2);
+//! assert_eq!((pairs[0].left, pairs[0].class), (0, MatchClass::Primary));
+//! assert_eq!((pairs[1].left, pairs[1].class), (1, MatchClass::Cross));
+//! assert!(pairs[0].score.unwrap() > pairs[1].score.unwrap());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::Metaphone3;
+use crate::packed::PackedKey;
+
+/// Which keys of a candidate pair matched, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MatchClass {
+    /// The primary keys are equal.
+    Primary,
+    /// The primary key of one name equals the secondary key of the other.
+    Cross,
+    /// Only the secondary keys are equal.
+    Secondary,
+}
+
+impl MatchClass {
+    /// Classifies two `(primary, secondary)` key pairs, or returns `None` if
+    /// no non-empty keys match.
+    #[must_use]
+    pub fn of(left: (PackedKey, PackedKey), right: (PackedKey, PackedKey)) -> Option<Self> {
+        let matches = |a: PackedKey, b: PackedKey| !a.is_empty() && a == b;
+        if matches(left.0, right.0) {
+            Some(MatchClass::Primary)
+        } else if matches(left.0, right.1) || matches(left.1, right.0) {
+            Some(MatchClass::Cross)
+        } else if matches(left.1, right.1) {
+            Some(MatchClass::Secondary)
+        } else {
+            None
+        }
+    }
+}
+
+/// A left and a right name whose keys collide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandidatePair {
+    /// Position of the name in the left collection.
+    pub left: usize,
+    /// Position of the name in the right collection.
+    pub right: usize,
+    /// Which keys matched.
+    pub class: MatchClass,
+    /// Jaro-Winkler similarity of the two names, if
+    /// [scoring](PhoneticIndex::with_scoring) is enabled.
+    pub score: Option<f64>,
+}
+
+/// Names of a left collection indexed by their keys. See the
+/// [module documentation](self).
+pub struct PhoneticIndex<'a, S> {
+    names: &'a [S],
+    keys: Vec<(PackedKey, PackedKey)>,
+    postings: HashMap<PackedKey, Vec<u32>>,
+    encoder: Metaphone3,
+    max_block: usize,
+    scoring: bool,
+}
+
+impl<'a, S: AsRef<str>> PhoneticIndex<'a, S> {
+    /// Encodes and indexes `names` with `encoder`, which is also used for the
+    /// right-hand names. Names without keys are never matched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` names.
+    pub fn new(mut encoder: Metaphone3, names: &'a [S]) -> Self {
+        let mut keys = Vec::with_capacity(names.len());
+        let mut postings: HashMap<PackedKey, Vec<u32>> = HashMap::new();
+        for (id, name) in names.iter().enumerate() {
+            let id = u32::try_from(id).expect("index holds at most u32::MAX names");
+            let (primary, secondary) = encoder.encode_packed(name.as_ref());
+            for key in [primary, secondary] {
+                if !key.is_empty() {
+                    postings.entry(key).or_default().push(id);
+                }
+            }
+            keys.push((primary, secondary));
+        }
+        PhoneticIndex {
+            names,
+            keys,
+            postings,
+            encoder,
+            max_block: usize::MAX,
+            scoring: false,
+        }
+    }
+
+    /// Ignores keys shared by more than `max_block` left names. Such keys are
+    /// too common to be useful for matching and would produce a huge number
+    /// of pairs. A pair found through another key is classified as if the
+    /// ignored keys were missing. Unlimited by default.
+    #[must_use]
+    pub fn with_max_block(mut self, max_block: usize) -> Self {
+        self.max_block = max_block;
+        self
+    }
+
+    /// Scores every candidate pair with [`jaro_winkler`]. Off by default.
+    #[must_use]
+    pub fn with_scoring(mut self, scoring: bool) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    /// Number of indexed names.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if no names are indexed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns a lazy iterator over the candidate pairs of each name in
+    /// `right`.
+    ///
+    /// Pairs come in right-hand order; the pairs of one right name are ordered
+    /// by [`MatchClass`], then by left position.
+    pub fn candidates<I>(&self, right: I) -> Candidates<'_, 'a, S, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Candidates {
+            index: self,
+            encoder: self.encoder.clone(),
+            right: right.into_iter(),
+            next_right: 0,
+            pending: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Fills `out` with the candidate pairs of one right name.
+    fn lookup(
+        &self,
+        encoder: &mut Metaphone3,
+        right: usize,
+        name: &str,
+        out: &mut Vec<CandidatePair>,
+    ) {
+        out.clear();
+        // Keys over the block limit neither find candidates nor classify
+        // them, on either side.
+        let usable = |key: PackedKey| match self.postings.get(&key) {
+            Some(ids) if ids.len() <= self.max_block => key,
+            _ => PackedKey::EMPTY,
+        };
+        let usable_keys = |(primary, secondary)| (usable(primary), usable(secondary));
+        let keys = usable_keys(encoder.encode_packed(name));
+        for key in [keys.0, keys.1] {
+            let Some(ids) = self.postings.get(&key) else {
+                continue;
+            };
+            for &id in ids {
+                let left = id as usize;
+                if let Some(class) = MatchClass::of(usable_keys(self.keys[left]), keys) {
+                    out.push(CandidatePair {
+                        left,
+                        right,
+                        class,
+                        score: None,
+                    });
+                }
+            }
+        }
+        out.sort_unstable_by_key(|pair| (pair.class, pair.left));
+        out.dedup_by_key(|pair| pair.left);
+        if self.scoring {
+            for pair in out {
+                pair.score = Some(jaro_winkler(self.names[pair.left].as_ref(), name));
+            }
+        }
+    }
+}
+
+/// Candidate pairs for a stream of right-hand names. Created by
+/// [`PhoneticIndex::candidates`].
+pub struct Candidates<'i, 'a, S, I> {
+    index: &'i PhoneticIndex<'a, S>,
+    encoder: Metaphone3,
+    right: I,
+    next_right: usize,
+    pending: Vec<CandidatePair>,
+    pos: usize,
+}
+
+impl<S, I> Iterator for Candidates<'_, '_, S, I>
+where
+    S: AsRef<str>,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = CandidatePair;
+
+    fn next(&mut self) -> Option<CandidatePair> {
+        while self.pos == self.pending.len() {
+            let name = self.right.next()?;
+            self.index.lookup(
+                &mut self.encoder,
+                self.next_right,
+                name.as_ref(),
+                &mut self.pending,
+            );
+            self.next_right += 1;
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(self.pending[self.pos - 1])
+    }
+}
+
+/// Jaro-Winkler similarity of two strings, compared case-insensitively: 1.0
+/// for equal strings, 0.0 for strings without common characters.
+#[must_use]
+#[allow(clippy::cast_precision_loss)] // the common prefix is at most 4
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().flat_map(char::to_uppercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_uppercase).collect();
+    let jaro = jaro(&a, &b);
+    let prefix = a.iter().zip(&b).take(4).take_while(|(x, y)| x == y).count();
+    jaro + 0.1 * prefix as f64 * (1.0 - jaro)
+}
+
+// Word lengths are far below 2^52, so the float conversions are exact.
+#[allow(clippy::cast_precision_loss)]
+fn jaro(a: &[char], b: &[char]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return if a.len() == b.len() { 1.0 } else { 0.0 };
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut b_matched = vec![false; b.len()];
+    let mut a_matches = Vec::new();
+    for (i, &c) in a.iter().enumerate() {
+        let hi = (i + window + 1).min(b.len());
+        for j in i.saturating_sub(window)..hi {
+            if !b_matched[j] && b[j] == c {
+                b_matched[j] = true;
+                a_matches.push(c);
+                break;
+            }
+        }
+    }
+    if a_matches.is_empty() {
+        return 0.0;
+    }
+    let b_matches = b.iter().zip(&b_matched).filter(|(_, m)| **m);
+    let transpositions = a_matches
+        .iter()
+        .zip(b_matches)
+        .filter(|(x, (y, _))| x != y)
+        .count()
+        / 2;
+    let m = a_matches.len() as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
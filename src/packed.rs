@@ -0,0 +1,230 @@
+//! Keys packed losslessly into a `u64`.
+//!
+//! Metaphone 3 keys are at most [`METAPH_MAX_LENGTH`] characters from a small
+//! alphabet: the built-in rules only emit the letters `A`–`Z` and `0` (for
+//! "th"). A [`PackedKey`] stores each character in 6 bits, first character in
+//! the most significant position, so that
+//!
+//! - packing and unpacking round-trip exactly,
+//! - comparing two packed keys as integers gives the same order as comparing
+//!   the key strings, and
+//! - hashing, sorting and storing keys works on plain `u64`s.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::packed::PackedKey;
+//!
+//! let mut encoder = Metaphone3::new();
+//! let (primary, secondary) = encoder.encode_packed("Smith");
+//! assert_eq!(primary.to_string(), "SM0");
+//! assert_eq!(secondary, "XMT".parse::<PackedKey>().unwrap());
+//! assert!(primary < secondary);
+//! assert_eq!(PackedKey::from_u64(primary.to_u64()), Some(primary));
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{METAPH_MAX_LENGTH, Metaphone3};
+
+const BITS: u32 = 6;
+const MASK: u64 = (1 << BITS) - 1;
+/// Character slots in a packed key.
+const SLOTS: u32 = 8;
+/// Number of bits used by a full-length key.
+const USED_BITS: u32 = BITS * SLOTS;
+
+const _: () = assert!(SLOTS as usize == METAPH_MAX_LENGTH);
+
+/// Bit offset of the given character slot; slot 0 is the most significant.
+const fn shift(slot: u32) -> u32 {
+    USED_BITS - BITS * (slot + 1)
+}
+
+/// A Metaphone 3 key packed into a `u64`. See the [module documentation](self).
+///
+/// The packed alphabet is `0`–`9` and `A`–`Z`, which covers every key the
+/// built-in rules produce. The empty key packs to `0`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PackedKey(u64);
+
+impl PackedKey {
+    /// The empty key, used for a missing secondary key.
+    pub const EMPTY: PackedKey = PackedKey(0);
+
+    /// Packs a key string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PackedKeyError::TooLong`] for keys over [`METAPH_MAX_LENGTH`]
+    /// characters and [`PackedKeyError::InvalidChar`] for characters outside
+    /// `0`–`9` and `A`–`Z`.
+    pub fn new(key: &str) -> Result<Self, PackedKeyError> {
+        let mut bits = 0;
+        let mut chars = key.chars();
+        for slot in 0..SLOTS {
+            let Some(c) = chars.next() else { break };
+            let code = char_code(c).ok_or(PackedKeyError::InvalidChar(c))?;
+            bits |= code << shift(slot);
+        }
+        if chars.next().is_some() {
+            return Err(PackedKeyError::TooLong);
+        }
+        Ok(PackedKey(bits))
+    }
+
+    /// Returns the packed representation.
+    #[must_use]
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Rebuilds a key from [`to_u64`](Self::to_u64) output. Returns `None` if
+    /// `bits` is not a valid packed key.
+    #[must_use]
+    pub fn from_u64(bits: u64) -> Option<Self> {
+        if bits >> USED_BITS != 0 {
+            return None;
+        }
+        let mut ended = false;
+        for slot in 0..SLOTS {
+            let code = (bits >> shift(slot)) & MASK;
+            match (code, ended) {
+                (0, _) => ended = true,
+                (_, true) => return None,
+                (code, false) if code_char(code).is_none() => return None,
+                _ => {}
+            }
+        }
+        Some(PackedKey(bits))
+    }
+
+    /// Number of characters in the key.
+    #[must_use]
+    pub fn len(self) -> usize {
+        self.chars().count()
+    }
+
+    /// Returns true for the empty key.
+    #[must_use]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterates over the characters of the key.
+    pub fn chars(self) -> impl Iterator<Item = char> {
+        (0..SLOTS)
+            .map(move |slot| (self.0 >> shift(slot)) & MASK)
+            .map_while(code_char)
+    }
+}
+
+/// True for the characters a key may contain, `0`–`9` and `A`–`Z`.
+pub(crate) fn is_key_char(c: char) -> bool {
+    c.is_ascii_digit() || c.is_ascii_uppercase()
+}
+
+/// Maps `0`–`9` to 1–10 and `A`–`Z` to 11–36, preserving ASCII order.
+fn char_code(c: char) -> Option<u64> {
+    match c {
+        '0'..='9' => Some(u64::from(c) - u64::from('0') + 1),
+        'A'..='Z' => Some(u64::from(c) - u64::from('A') + 11),
+        _ => None,
+    }
+}
+
+fn code_char(code: u64) -> Option<char> {
+    // Codes are at most 6 bits, so the narrowing casts are lossless.
+    #[allow(clippy::cast_possible_truncation)]
+    match code {
+        1..=10 => Some(char::from(b'0' + (code - 1) as u8)),
+        11..=36 => Some(char::from(b'A' + (code - 11) as u8)),
+        _ => None,
+    }
+}
+
+impl fmt::Display for PackedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl fmt::Debug for PackedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PackedKey(\"{self}\")")
+    }
+}
+
+impl FromStr for PackedKey {
+    type Err = PackedKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PackedKey::new(s)
+    }
+}
+
+impl TryFrom<&str> for PackedKey {
+    type Error = PackedKeyError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        PackedKey::new(s)
+    }
+}
+
+impl From<PackedKey> for u64 {
+    fn from(key: PackedKey) -> Self {
+        key.0
+    }
+}
+
+impl PartialEq<str> for PackedKey {
+    fn eq(&self, other: &str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl PartialEq<&str> for PackedKey {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// An error packing a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PackedKeyError {
+    /// The key is longer than [`METAPH_MAX_LENGTH`] characters.
+    TooLong,
+    /// The key contains a character outside `0`–`9` and `A`–`Z`.
+    InvalidChar(char),
+}
+
+impl fmt::Display for PackedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackedKeyError::TooLong => {
+                write!(f, "key is longer than {METAPH_MAX_LENGTH} characters")
+            }
+            PackedKeyError::InvalidChar(c) => write!(f, "character {c:?} cannot be packed"),
+        }
+    }
+}
+
+impl core::error::Error for PackedKeyError {}
+
+impl Metaphone3 {
+    /// Encodes a word like [`encode`](Self::encode), returning the keys as
+    /// [`PackedKey`]s. A missing secondary key is [`PackedKey::EMPTY`].
+    ///
+    /// Every key packs: keys are truncated to [`METAPH_MAX_LENGTH`], and
+    /// neither the built-in rules, [custom rules](crate::rules) nor
+    /// [exceptions](crate::exceptions) can emit characters outside the packed
+    /// alphabet.
+    #[allow(clippy::missing_panics_doc)] // see above
+    pub fn encode_packed(&mut self, word: &str) -> (PackedKey, PackedKey) {
+        let (primary, secondary) = self.encode(word);
+        let pack = |key: &str| PackedKey::new(key).expect("keys use the packed alphabet");
+        (pack(&primary), pack(&secondary))
+    }
+}
@@ -0,0 +1,385 @@
+//! Custom spelling rules that extend the built-in rule engine.
+//!
+//! The encoder walks the (uppercased) input one letter at a time and hands each
+//! letter to a built-in `encode_*` routine. A [`RuleSet`] adds user rules to
+//! that walk, registered per letter:
+//!
+//! - **before** rules run first; the first one that returns `true` handles the
+//!   letter and the built-in routine is skipped.
+//! - **after** rules run once the built-in routine is done (or for letters that
+//!   have no built-in routine, such as `Ø` or digits); the first one that
+//!   returns `true` stops the rest.
+//!
+//! A rule inspects the word through a [`RuleContext`], using the same
+//! primitives as the built-in rules (`string_at`, `string_at_start`,
+//! `string_at_end`, ...), appends to the keys with [`RuleContext::add_alt`] and
+//! friends, and may [`advance`](RuleContext::advance) the cursor past letters it
+//! consumed. Whatever a rule emits or advances is rolled back if it returns
+//! `false`.
+//!
+//! Keys only hold `0`–`9` and `A`–`Z`, the [packed](crate::packed) alphabet.
+//! The `add` methods return an error for any other character and append
+//! nothing, so a rule can never produce a key that cannot be packed.
+//!
+//! # Example
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::rules::{RuleContext, RuleSet};
+//!
+//! // Pinyin: initial "X" before "I" sounds like "SH".
+//! let rules = RuleSet::new().before('X', |ctx: &mut RuleContext<'_>| {
+//!     if ctx.string_at_start(0, &["XI"]) {
+//!         return ctx.add('X').is_ok();
+//!     }
+//!     false
+//! });
+//!
+//! let mut encoder = Metaphone3::new().with_rules(rules);
+//! assert_eq!(encoder.encode("Xiang").0, "XNK");
+//! assert_eq!(encoder.encode("Xavier").0, "SFR");
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::Metaphone3;
+use crate::fingerprint::Fnv1a;
+use crate::packed::{PackedKeyError, is_key_char};
+
+/// A custom spelling rule. See the [module documentation](self).
+///
+/// Implemented for any `Fn(&mut RuleContext<'_>) -> bool`, so closures can be
+/// registered directly.
+pub trait Rule: Send + Sync {
+    /// Tries to encode the letter at the cursor. Returns `true` if the rule
+    /// applied.
+    fn apply(&self, ctx: &mut RuleContext<'_>) -> bool;
+}
+
+impl<F> Rule for F
+where
+    F: Fn(&mut RuleContext<'_>) -> bool + Send + Sync,
+{
+    fn apply(&self, ctx: &mut RuleContext<'_>) -> bool {
+        self(ctx)
+    }
+}
+
+type Rules = BTreeMap<char, Vec<Arc<dyn Rule>>>;
+
+/// Custom rules, registered per letter. Rules for the same letter run in
+/// registration order.
+///
+/// Attach one to an encoder with
+/// [`Metaphone3::with_rules`](crate::Metaphone3::with_rules).
+///
+/// Rules are code and cannot be hashed, so the
+/// [fingerprint](crate::fingerprint) of an encoder covers the letters its
+/// rules are registered for and the rule set's
+/// [version](RuleSet::with_version). Change the version whenever a rule
+/// changes what it emits.
+#[derive(Clone, Default)]
+pub struct RuleSet {
+    before: Rules,
+    after: Rules,
+    version: String,
+}
+
+impl RuleSet {
+    /// Creates an empty rule set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a rule that runs before the built-in rules for `letter`.
+    /// The letter is matched case-insensitively.
+    #[must_use]
+    pub fn before(mut self, letter: char, rule: impl Rule + 'static) -> Self {
+        Self::register(&mut self.before, letter, rule);
+        self
+    }
+
+    /// Registers a rule that runs after the built-in rules for `letter`.
+    /// The letter is matched case-insensitively.
+    #[must_use]
+    pub fn after(mut self, letter: char, rule: impl Rule + 'static) -> Self {
+        Self::register(&mut self.after, letter, rule);
+        self
+    }
+
+    /// Sets the version of the rules, a caller-chosen name such as `"pinyin-2"`
+    /// that goes into the [fingerprint](crate::fingerprint). Empty by
+    /// default.
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// The version set with [`with_version`](Self::with_version).
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Number of registered rules.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.before
+            .values()
+            .chain(self.after.values())
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Returns true if no rules are registered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Feeds the version and the letters the rules are registered for to
+    /// `hash`, for [`Metaphone3::fingerprint`](crate::Metaphone3::fingerprint).
+    pub(crate) fn hash_into(&self, hash: &mut Fnv1a) {
+        hash.write_str(&self.version);
+        for rules in [&self.before, &self.after] {
+            hash.write_u64(rules.len() as u64);
+            for (&letter, rules) in rules {
+                hash.write_u64(u64::from(letter));
+                hash.write_u64(rules.len() as u64);
+            }
+        }
+    }
+
+    fn register(rules: &mut Rules, letter: char, rule: impl Rule + 'static) {
+        let rule: Arc<dyn Rule> = Arc::new(rule);
+        for upper in letter.to_uppercase() {
+            rules.entry(upper).or_default().push(Arc::clone(&rule));
+        }
+    }
+
+    /// Runs the before rules for `letter`; returns true if one applied.
+    pub(crate) fn apply_before(&self, letter: char, encoder: &mut Metaphone3) -> bool {
+        Self::apply(&self.before, letter, encoder)
+    }
+
+    /// Runs the after rules for `letter`; returns true if one applied.
+    pub(crate) fn apply_after(&self, letter: char, encoder: &mut Metaphone3) -> bool {
+        Self::apply(&self.after, letter, encoder)
+    }
+
+    fn apply(rules: &Rules, letter: char, encoder: &mut Metaphone3) -> bool {
+        let Some(rules) = rules.get(&letter) else {
+            return false;
+        };
+        for rule in rules {
+            let idx = encoder.idx;
+            let prim_len = encoder.prim_buf.len();
+            let second_len = encoder.second_buf.len();
+            let exact_lens = (encoder.exact_bufs.as_ref()).map(|(p, s)| (p.len(), s.len()));
+            if rule.apply(&mut RuleContext { encoder }) {
+                return true;
+            }
+            encoder.idx = idx;
+            encoder.prim_buf.truncate(prim_len);
+            encoder.second_buf.truncate(second_len);
+            if let (Some((prim, second)), Some((prim_len, second_len))) =
+                (&mut encoder.exact_bufs, exact_lens)
+            {
+                prim.truncate(prim_len);
+                second.truncate(second_len);
+            }
+        }
+        false
+    }
+}
+
+/// The encoder state visible to a custom [`Rule`].
+///
+/// Offsets are relative to the cursor, as in the built-in rules: `0` is the
+/// letter being encoded, `-1` the one before it. All matching is against the
+/// uppercased input.
+pub struct RuleContext<'a> {
+    encoder: &'a mut Metaphone3,
+}
+
+impl RuleContext<'_> {
+    /// Index of the cursor in the uppercased input.
+    #[must_use]
+    pub fn idx(&self) -> usize {
+        self.encoder.idx
+    }
+
+    /// Number of characters in the uppercased input.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.encoder.length
+    }
+
+    /// True if the input is empty, which is never the case while rules run.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.encoder.length == 0
+    }
+
+    /// The letter at `offset` from the cursor, if any.
+    #[must_use]
+    pub fn letter(&self, offset: isize) -> Option<char> {
+        let idx = self.encoder.idx.checked_add_signed(offset)?;
+        self.encoder.in_buf.get(idx).copied()
+    }
+
+    /// Whether vowel encoding is enabled.
+    #[must_use]
+    pub fn encode_vowels(&self) -> bool {
+        self.encoder.encode_vowels
+    }
+
+    /// Whether exact encoding is enabled.
+    #[must_use]
+    pub fn encode_exact(&self) -> bool {
+        self.encoder.encode_exact
+    }
+
+    /// True if the letter at `offset` is `c`.
+    #[must_use]
+    pub fn char_at(&self, offset: isize, c: char) -> bool {
+        self.encoder.char_at(offset, c)
+    }
+
+    /// True if the letter at `offset` is a vowel (`A E I O U Y`).
+    #[must_use]
+    pub fn is_vowel_at(&self, offset: isize) -> bool {
+        self.encoder.is_vowel_at(offset)
+    }
+
+    /// True if one of `vals` occurs at `offset`.
+    ///
+    /// As in the built-in rules, list `vals` shortest first: matching stops at
+    /// the first candidate that would run past the end of the word.
+    #[must_use]
+    pub fn string_at(&self, offset: isize, vals: &[&str]) -> bool {
+        self.encoder.string_at(offset, vals)
+    }
+
+    /// True if `offset` is the start of the word and one of `vals` occurs there.
+    #[must_use]
+    pub fn string_at_start(&self, offset: isize, vals: &[&str]) -> bool {
+        self.encoder.string_at_start(offset, vals)
+    }
+
+    /// True if one of `vals` occurs at `offset` and runs to the end of the word.
+    #[must_use]
+    pub fn string_at_end(&self, offset: isize, vals: &[&str]) -> bool {
+        self.encoder.string_at_end(offset, vals)
+    }
+
+    /// True if the word starts with one of `vals`.
+    #[must_use]
+    pub fn string_start(&self, vals: &[&str]) -> bool {
+        self.encoder.string_start(vals)
+    }
+
+    /// True if the word ends with one of `vals`.
+    #[must_use]
+    pub fn string_end(&self, vals: &[&str]) -> bool {
+        self.encoder.string_end(vals)
+    }
+
+    /// True if the word is exactly one of `vals`.
+    #[must_use]
+    pub fn string_exact(&self, vals: &[&str]) -> bool {
+        self.encoder.string_exact(vals)
+    }
+
+    /// True if the word contains `val` anywhere.
+    #[must_use]
+    pub fn string_contains(&self, val: &str) -> bool {
+        self.encoder.string_contains(val)
+    }
+
+    /// True if the word looks Slavic or Germanic, as judged by the built-in
+    /// rules.
+    #[must_use]
+    pub fn is_slavo_germanic(&self) -> bool {
+        self.encoder.looks_slavo_germanic()
+    }
+
+    /// Appends `c` to both keys. `'\0'` appends nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PackedKeyError::InvalidChar`], and appends nothing, if `c` is
+    /// not one of `0`–`9` and `A`–`Z`. The same holds for the other `add`
+    /// methods.
+    pub fn add(&mut self, c: char) -> Result<(), PackedKeyError> {
+        self.encoder.metaph_add(key_char(c)?);
+        Ok(())
+    }
+
+    /// Appends `prim` to the primary key and `second` to the secondary key.
+    /// `'\0'` appends nothing.
+    ///
+    /// # Errors
+    ///
+    /// See [`add`](Self::add).
+    pub fn add_alt(&mut self, prim: char, second: char) -> Result<(), PackedKeyError> {
+        let (prim, second) = (key_char(prim)?, key_char(second)?);
+        self.encoder.metaph_add_alt(prim, second);
+        Ok(())
+    }
+
+    /// Appends `prim` to the primary key and `second` to the secondary key.
+    ///
+    /// # Errors
+    ///
+    /// See [`add`](Self::add).
+    pub fn add_str(&mut self, prim: &str, second: &str) -> Result<(), PackedKeyError> {
+        key_str(prim)?;
+        key_str(second)?;
+        self.encoder.metaph_add_str(prim, second);
+        Ok(())
+    }
+
+    /// Appends `exact` in exact mode and `approx` otherwise, to both keys.
+    ///
+    /// # Errors
+    ///
+    /// See [`add`](Self::add).
+    pub fn add_exact_approx(&mut self, exact: char, approx: char) -> Result<(), PackedKeyError> {
+        let (exact, approx) = (key_char(exact)?, key_char(approx)?);
+        self.encoder.metaph_add_exact_approx(exact, approx);
+        Ok(())
+    }
+
+    /// Moves the cursor `n` letters further, at most to the last letter the
+    /// rules encode: the end of the word, or the letter before an
+    /// [exception](crate::exceptions) suffix. The encoder always steps past
+    /// the current letter afterwards, so a rule that consumed `"TH"` advances
+    /// by 1.
+    pub fn advance(&mut self, n: usize) {
+        let encoder = &mut *self.encoder;
+        encoder.idx = encoder.idx.saturating_add(n).min(encoder.end - 1);
+    }
+}
+
+/// `c` if keys may hold it or it is `'\0'`, which appends nothing.
+fn key_char(c: char) -> Result<char, PackedKeyError> {
+    if c == '\0' || is_key_char(c) {
+        Ok(c)
+    } else {
+        Err(PackedKeyError::InvalidChar(c))
+    }
+}
+
+/// Checks that keys may hold every character of `s`.
+fn key_str(s: &str) -> Result<(), PackedKeyError> {
+    match s.chars().find(|&c| !is_key_char(c)) {
+        Some(c) => Err(PackedKeyError::InvalidChar(c)),
+        None => Ok(()),
+    }
+}
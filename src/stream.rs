@@ -0,0 +1,178 @@
+//! Streaming adapters that encode many words with one reused encoder.
+//!
+//! [`EncodeLines`] reads a [`BufRead`] line by line into a single reused
+//! buffer, and [`EncodeIter`] wraps any iterator of `&str`. Neither allocates
+//! per word beyond what [`Metaphone3::encode`] itself does (keys of up to 8
+//! characters are stored inline), so memory stays bounded no matter how large
+//! the input is.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//!
+//! let csv = "1,Smith,NY\n2,Schmidt,CA\n";
+//! let mut encoder = Metaphone3::new();
+//! let mut lines = encoder.encode_lines(csv.as_bytes()).with_column(1);
+//!
+//! while let Some(record) = lines.next_record() {
+//!     let (name, primary, secondary) = record?;
+//!     println!("{name}: {primary} {secondary}");
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+use crate::csv::{self, BadQuotes};
+use crate::{Metaphone3, String};
+
+/// Encodes every line of a reader. Created by [`Metaphone3::encode_lines`].
+///
+/// This is a *lending* iterator: each record borrows the reused line buffer, so
+/// it is read with [`next_record`](Self::next_record) in a `while let` loop
+/// rather than with `for`.
+pub struct EncodeLines<'e, R> {
+    encoder: &'e mut Metaphone3,
+    reader: R,
+    line: std::string::String,
+    // The selected column, if unquoting it needed a copy.
+    field: std::string::String,
+    column: Option<usize>,
+    delimiter: char,
+}
+
+impl<'e, R: BufRead> EncodeLines<'e, R> {
+    pub(crate) fn new(encoder: &'e mut Metaphone3, reader: R) -> Self {
+        EncodeLines {
+            encoder,
+            reader,
+            line: std::string::String::new(),
+            field: std::string::String::new(),
+            column: None,
+            delimiter: ',',
+        }
+    }
+
+    /// Encodes only the given 0-based column of each line instead of the whole
+    /// line. Columns are split on the [delimiter](Self::with_delimiter) and may
+    /// be quoted as in CSV (RFC 4180): `"Smith, Jr."` is one column, `""`
+    /// inside quotes is a double quote, and a quoted column may span lines. A
+    /// line without that column yields an empty word.
+    #[must_use]
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    /// Sets the column delimiter used by [`with_column`](Self::with_column).
+    /// Defaults to `,`.
+    #[must_use]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Reads the next line and returns `(word, primary, secondary)`, or `None`
+    /// at end of input. The word has surrounding whitespace, including the
+    /// line ending, trimmed. Blank lines yield an empty word and empty keys,
+    /// so records stay aligned with input lines (or, with a column selected,
+    /// with CSV records).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the reader, including invalid UTF-8, and
+    /// [`io::ErrorKind::InvalidData`] if the selected column has a stray
+    /// double quote or the input ends inside a quoted column.
+    pub fn next_record(&mut self) -> Option<io::Result<(&str, String, String)>> {
+        self.line.clear();
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        if self.column.is_some() {
+            while csv::is_open(&self.line) {
+                match self.reader.read_line(&mut self.line) {
+                    Ok(0) => return Some(Err(invalid_data("unclosed quoted column"))),
+                    Ok(_) => {}
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+
+        let line = self.line.trim_end_matches(['\n', '\r']);
+        let word = match self.column {
+            Some(column) => match csv::fields(line, self.delimiter).nth(column) {
+                None => "",
+                Some(Ok(Cow::Borrowed(field))) => field,
+                Some(Ok(Cow::Owned(field))) => {
+                    self.field = field;
+                    &self.field
+                }
+                Some(Err(BadQuotes)) => {
+                    return Some(Err(invalid_data("stray double quote in column")));
+                }
+            },
+            None => line,
+        }
+        .trim();
+        let (primary, secondary) = self.encoder.encode(word);
+        Some(Ok((word, primary, secondary)))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Encodes every word of an iterator. Created by [`Metaphone3::encode_iter`].
+pub struct EncodeIter<'e, I> {
+    encoder: &'e mut Metaphone3,
+    words: I,
+}
+
+impl<'e, I> EncodeIter<'e, I> {
+    pub(crate) fn new(encoder: &'e mut Metaphone3, words: I) -> Self {
+        EncodeIter { encoder, words }
+    }
+}
+
+impl<'a, I> Iterator for EncodeIter<'_, I>
+where
+    I: Iterator<Item = &'a str>,
+{
+    type Item = (&'a str, String, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let word = self.words.next()?;
+        let (primary, secondary) = self.encoder.encode(word);
+        Some((word, primary, secondary))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.words.size_hint()
+    }
+}
+
+impl Metaphone3 {
+    /// Returns a streaming encoder over the lines of `reader`. See
+    /// [`EncodeLines`].
+    pub fn encode_lines<R: BufRead>(&mut self, reader: R) -> EncodeLines<'_, R> {
+        EncodeLines::new(self, reader)
+    }
+
+    /// Returns an iterator yielding `(word, primary, secondary)` for each word.
+    ///
+    /// ```
+    /// # use metaphone3::Metaphone3;
+    /// let mut encoder = Metaphone3::new();
+    /// let keys: Vec<_> = encoder.encode_iter(["Smith", "Aachen"]).collect();
+    /// assert_eq!(keys[1], ("Aachen", "AKN".into(), "AXN".into()));
+    /// ```
+    pub fn encode_iter<'a, I>(&mut self, words: I) -> EncodeIter<'_, I::IntoIter>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        EncodeIter::new(self, words.into_iter())
+    }
+}
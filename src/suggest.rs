@@ -0,0 +1,246 @@
+//! "Did you mean" suggestions for phonetically misspelled words.
+//!
+//! A [`Suggester`] indexes the words of a frequency list by their keys. For a
+//! misspelled word it returns the dictionary words that sound the same, that
+//! is share a key with it, ranked by how often they occur and how close their
+//! spelling is.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::suggest::Suggester;
+//!
+//! let list = "the\t23135851162\nphonetic\t1289447\nfanatic\t627834\n";
+//! let suggester = Suggester::from_frequency_list(Metaphone3::new(), list.as_bytes())?;
+//! let suggestions = suggester.suggest("fonetik", 5);
+//! assert_eq!(suggestions[0].word, "phonetic");
+//! assert_eq!(suggestions[0].distance, 3);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Frequency lists hold one word per line, either every word followed by
+//! whitespace and its count, or none. A list without counts, such as
+//! `testdata/count_1w.txt`, is taken to be sorted by frequency, most frequent
+//! first, and to follow Zipf's law: of `n` words, the word at rank `r` is
+//! given the count `n / r`.
+//!
+//! [`Suggester::suggest`] takes `&self`, so one dictionary can answer queries
+//! from several threads, and the suggestions of several queries can be kept
+//! side by side.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use crate::Metaphone3Config;
+use crate::matching::MatchClass;
+use crate::packed::PackedKey;
+
+/// How many times as frequent a word must be to make up for one more edit.
+const EDIT_FACTOR: f64 = 5.0;
+
+/// A dictionary word suggested by [`Suggester::suggest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Suggestion<'a> {
+    /// The dictionary word.
+    pub word: &'a str,
+    /// How often the word occurs, from the frequency list or its rank.
+    pub count: u64,
+    /// [Levenshtein distance](levenshtein) between the word and the query.
+    pub distance: usize,
+    /// Which keys of the word and the query matched.
+    pub class: MatchClass,
+}
+
+/// Dictionary words indexed by their keys. See the
+/// [module documentation](self).
+#[derive(Clone)]
+pub struct Suggester {
+    config: Metaphone3Config,
+    words: Vec<Box<str>>,
+    counts: Vec<u64>,
+    keys: Vec<(PackedKey, PackedKey)>,
+    postings: HashMap<PackedKey, Vec<u32>>,
+    max_distance: usize,
+}
+
+impl Suggester {
+    /// Creates an empty dictionary that encodes words and queries with
+    /// `config`, a [`Metaphone3Config`] or a [`Metaphone3`](crate::Metaphone3).
+    #[must_use]
+    pub fn new(config: impl Into<Metaphone3Config>) -> Self {
+        Suggester {
+            config: config.into(),
+            words: Vec::new(),
+            counts: Vec::new(),
+            keys: Vec::new(),
+            postings: HashMap::new(),
+            max_distance: usize::MAX,
+        }
+    }
+
+    /// Reads a frequency list into a new dictionary. See the
+    /// [module documentation](self) for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of the reader, and [`io::ErrorKind::InvalidData`] for
+    /// a count that is not a number or a list where only some words have
+    /// counts.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`insert`](Self::insert).
+    pub fn from_frequency_list<R: BufRead>(
+        config: impl Into<Metaphone3Config>,
+        reader: R,
+    ) -> io::Result<Self> {
+        let mut suggester = Suggester::new(config);
+        let mut counted = None;
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let invalid = |message: std::string::String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {message}", number + 1),
+                )
+            };
+            let count = fields.next();
+            if *counted.get_or_insert(count.is_some()) != count.is_some() {
+                return Err(invalid(
+                    "either every word or none must have a count".into(),
+                ));
+            }
+            let count = match count {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid count {count:?}")))?,
+                None => 0,
+            };
+            suggester.insert(word, count);
+        }
+        if counted == Some(false) {
+            let len = suggester.counts.len() as u64;
+            for (rank, count) in (1..).zip(&mut suggester.counts) {
+                *count = len.div_ceil(rank);
+            }
+        }
+        Ok(suggester)
+    }
+
+    /// Adds a word that occurs `count` times. Words without keys are never
+    /// suggested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` words.
+    pub fn insert(&mut self, word: &str, count: u64) {
+        let id = u32::try_from(self.words.len()).expect("dictionary holds at most u32::MAX words");
+        let (primary, secondary) = self.config.encode_packed(word);
+        for key in [primary, secondary] {
+            if !key.is_empty() {
+                self.postings.entry(key).or_default().push(id);
+            }
+        }
+        self.words.push(word.into());
+        self.counts.push(count);
+        self.keys.push((primary, secondary));
+    }
+
+    /// Only suggests words within `max_distance` edits of the query.
+    /// Unlimited by default.
+    #[must_use]
+    pub fn with_max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Number of dictionary words.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns true if the dictionary is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns up to `limit` dictionary words that sound like `word`, best
+    /// first. The query itself is never suggested, whatever its case.
+    ///
+    /// Words are ranked by count, where each edit divides the count by 5:
+    /// a word one edit further away must be 5 times as frequent to rank
+    /// the same. Ties go to the closer word, then alphabetically.
+    #[must_use]
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<Suggestion<'_>> {
+        let keys = self.config.encode_packed(word);
+        let mut ids: Vec<u32> = [keys.0, keys.1]
+            .iter()
+            .filter_map(|key| self.postings.get(key))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        let mut scored: Vec<(f64, Suggestion<'_>)> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let id = id as usize;
+                let candidate = &*self.words[id];
+                let distance = levenshtein(candidate, word);
+                if distance == 0 || distance > self.max_distance {
+                    return None;
+                }
+                let suggestion = Suggestion {
+                    word: candidate,
+                    count: self.counts[id],
+                    distance,
+                    class: MatchClass::of(self.keys[id], keys)?,
+                };
+                Some((score(&suggestion), suggestion))
+            })
+            .collect();
+        scored.sort_unstable_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .total_cmp(a_score)
+                .then(a.distance.cmp(&b.distance))
+                .then(a.word.cmp(b.word))
+        });
+        scored.truncate(limit);
+        scored
+            .into_iter()
+            .map(|(_, suggestion)| suggestion)
+            .collect()
+    }
+}
+
+/// The log of the count, less the log of [`EDIT_FACTOR`] per edit.
+#[allow(clippy::cast_precision_loss)] // a ranking does not need every bit
+fn score(suggestion: &Suggestion<'_>) -> f64 {
+    (suggestion.count as f64).ln() - suggestion.distance as f64 * EDIT_FACTOR.ln()
+}
+
+/// Levenshtein distance of two strings, compared case-insensitively: the
+/// number of inserted, deleted or substituted characters that turn one into
+/// the other.
+#[must_use]
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_uppercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_uppercase).collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
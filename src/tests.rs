@@ -1,8 +1,37 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
 
-use crate::Metaphone3;
+use proptest::prelude::*;
+
+#[cfg(feature = "std")]
+use crate::blocking::BlockingStrategy;
+#[cfg(feature = "std")]
+use crate::cache::{CacheStats, CachedMetaphone3};
+#[cfg(feature = "std")]
+use crate::cluster::Clusterer;
+use crate::detailed::Heuristics;
+#[cfg(feature = "std")]
+use crate::disk::{DiskIndex, DiskIndexBuilder, DiskIndexError};
+use crate::exceptions::{ExceptionDictionary, ExceptionError, MalformedReason};
+use crate::fingerprint::Fingerprint;
+#[cfg(feature = "std")]
+use crate::fingerprint::{ALGORITHM_VERSION, Fnv1a};
+#[cfg(feature = "std")]
+use crate::golden::{self, GoldenEncoder, GoldenRecord};
+#[cfg(feature = "std")]
+use crate::matching::{MatchClass, PhoneticIndex, jaro_winkler};
+use crate::packed::{PackedKey, PackedKeyError};
+use crate::rules::{RuleContext, RuleSet};
+#[cfg(feature = "std")]
+use crate::suggest::{Suggester, levenshtein};
+use crate::validate::EncodeError;
+use crate::{Metaphone3, Metaphone3Config, Mode};
 
 #[test]
 fn test_basic_words() {
@@ -43,6 +72,7 @@ fn test_aaberg() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_name_files() -> Result<(), Box<dyn std::error::Error>> {
     let testdata_dir = Path::new("testdata");
     if !testdata_dir.exists() {
@@ -50,6 +80,7 @@ fn test_name_files() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    let mut encoder = GoldenEncoder::new();
     for entry in std::fs::read_dir(testdata_dir)? {
         let entry = entry?;
         let path = entry.path();
@@ -57,101 +88,1315 @@ fn test_name_files() -> Result<(), Box<dyn std::error::Error>> {
             continue;
         }
 
-        let file = File::open(&path)?;
-        let reader = BufReader::new(file);
-        let mut csv_reader = csv::ReaderBuilder::new()
-            .delimiter(b',')
-            .has_headers(false)
-            .from_reader(reader);
+        let records = golden::read_corpus(BufReader::new(File::open(&path)?))?;
+        let diff = encoder.check(&records);
+        print!("{diff}");
+
+        assert!(
+            diff.is_empty(),
+            "Errors when processing {path:?}: Enc={} EncEV={} EncE={} EncV={}",
+            diff.changed(Mode::Enc),
+            diff.changed(Mode::EncEV),
+            diff.changed(Mode::EncE),
+            diff.changed(Mode::EncV)
+        );
+    }
+
+    Ok(())
+}
+
+/// `test_name_files` checks the corpora with one `encode` call per mode; this
+/// checks that `encode_all_modes` agrees with them.
+#[test]
+#[cfg(feature = "std")]
+fn test_name_files_all_modes() -> Result<(), Box<dyn std::error::Error>> {
+    let testdata_dir = Path::new("testdata");
+    if !testdata_dir.exists() {
+        eprintln!("Skipping test_name_files_all_modes: testdata/ directory not found");
+        return Ok(());
+    }
+
+    let mut encoder = Metaphone3::new();
+    for entry in std::fs::read_dir(testdata_dir)? {
+        let path = entry?.path();
+        if path.extension() != Some("test".as_ref()) {
+            continue;
+        }
+        for record in golden::read_corpus(BufReader::new(File::open(&path)?))? {
+            assert_eq!(
+                encoder.encode_all_modes(&record.word),
+                record.keys,
+                "{}",
+                record.word
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_latin1() {
+    let mut encoder = Metaphone3::new();
+    // "Muñoz", "Gonçalves", "Groß" in ISO-8859-1
+    for (latin1, utf8) in [
+        (&b"Mu\xF1oz"[..], "Muñoz"),
+        (b"Gon\xE7alves", "Gonçalves"),
+        (b"Gro\xDF", "Groß"),
+    ] {
+        assert_eq!(encoder.encode_latin1(latin1), encoder.encode(utf8));
+    }
+    assert_eq!(encoder.encode_latin1(b""), ("".into(), "".into()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_encode_lines() -> std::io::Result<()> {
+    let input = "Smith\r\nSchmidt \n\nAaberg";
+    let mut encoder = Metaphone3::new();
+    let mut lines = encoder.encode_lines(input.as_bytes());
+    let mut records = Vec::new();
+    while let Some(record) = lines.next_record() {
+        let (word, primary, secondary) = record?;
+        records.push((word.to_owned(), primary, secondary));
+    }
+    assert_eq!(
+        records,
+        [
+            ("Smith".into(), "SM0".into(), "XMT".into()),
+            ("Schmidt".into(), "XMT".into(), "".into()),
+            (std::string::String::new(), "".into(), "".into()),
+            ("Aaberg".into(), "APRK".into(), "".into()),
+        ]
+    );
+
+    let csv = "id;name\n7; Aachen ;x\n8\n";
+    let mut lines = encoder
+        .encode_lines(csv.as_bytes())
+        .with_column(1)
+        .with_delimiter(';');
+    let mut words = Vec::new();
+    while let Some(record) = lines.next_record() {
+        let (word, primary, _) = record?;
+        words.push(format!("{word}={primary}"));
+    }
+    assert_eq!(words, ["name=NM", "Aachen=AKN", "="]);
+
+    // Quoted columns, as in CSV; whitespace is trimmed as in whole lines.
+    let csv = "\"Smith, Jr.\",\"NY\"\n\"O\"\"Brien\", Schmidt \n\"a\nb\",Aaberg\n";
+    let mut lines = encoder.encode_lines(csv.as_bytes()).with_column(1);
+    let mut words = Vec::new();
+    while let Some(record) = lines.next_record() {
+        words.push(record?.0.to_owned());
+    }
+    assert_eq!(words, ["NY", "Schmidt", "Aaberg"]);
+    let mut lines = encoder.encode_lines(csv.as_bytes()).with_column(0);
+    let mut words = Vec::new();
+    while let Some(record) = lines.next_record() {
+        words.push(record?.0.to_owned());
+    }
+    assert_eq!(words, ["Smith, Jr.", "O\"Brien", "a\nb"]);
+
+    for bad in ["x,O\"Brien\n", "x,\"Smith\"\"\n", "x,\"Smith\"s\n"] {
+        let mut lines = encoder.encode_lines(bad.as_bytes()).with_column(1);
+        let err = lines.next_record().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{bad:?}");
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_encode_iter() {
+    let mut encoder = Metaphone3::new().with_encode_vowels(true);
+    let keys: Vec<_> = encoder
+        .encode_iter(["supernode", "", "Aaberg"])
+        .map(|(_, primary, _)| primary)
+        .collect();
+    assert_eq!(keys, ["SAPARNAT", "", "APARK"]);
+    // The encoder is only borrowed and keeps its configuration.
+    assert_eq!(encoder.encode("supernode").0, "SAPARNAT");
+}
+
+#[test]
+fn test_packed_keys() {
+    let mut encoder = Metaphone3::new();
+    assert_eq!(
+        encoder.encode_packed("Aachen"),
+        (
+            PackedKey::new("AKN").unwrap(),
+            PackedKey::new("AXN").unwrap()
+        )
+    );
+    assert_eq!(encoder.encode_packed("Aaberg").1, PackedKey::EMPTY);
+    assert_eq!(
+        format!("{:?}", PackedKey::new("SM0").unwrap()),
+        "PackedKey(\"SM0\")"
+    );
+
+    assert_eq!(PackedKey::new("ABCDEFGHI"), Err(PackedKeyError::TooLong));
+    assert_eq!(PackedKey::new("sm0"), Err(PackedKeyError::InvalidChar('s')));
+    assert_eq!(PackedKey::from_u64(1 << 48), None);
+    // A character after the end of the key.
+    assert_eq!(PackedKey::from_u64(1), None);
+    // Code 37 is past `Z`.
+    assert_eq!(PackedKey::from_u64(37 << 42), None);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_golden_record_round_trip() {
+    let line = "Aachen,AKN,AXN,AKAN,AXAN,AKN,AXN,AKAN,AXAN";
+    let record = GoldenRecord::parse(line).unwrap();
+    assert_eq!(record.word, "Aachen");
+    assert_eq!(record.keys(Mode::EncE), &("AKN".into(), "AXN".into()));
+    assert_eq!(record.to_string(), line);
+
+    assert!(GoldenRecord::parse("Aachen,AKN,AXN").is_none());
+    assert!(GoldenRecord::parse(&format!("{line},")).is_none());
+    assert!(GoldenRecord::parse(&format!("O\"{line}")).is_none());
+    assert!(GoldenRecord::parse(&format!("\"O\"{line}")).is_none());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_golden_corpus_quoting() -> Result<(), golden::GoldenError> {
+    let words = [
+        "Smith, Jr.",
+        "O\"Brien",
+        "\"Nike\"",
+        "Line\nbreak",
+        "",
+        "Aachen",
+    ];
+    let mut encoder = GoldenEncoder::new();
+    let mut corpus = Vec::new();
+    encoder.write_corpus(&mut corpus, words)?;
+    let corpus = String::from_utf8(corpus).unwrap();
+    assert!(corpus.starts_with("\"Smith, Jr.\",SM0JR,XMTJR,"));
+    assert!(corpus.contains("\n\"O\"\"Brien\",") && corpus.contains("\n\"\"\"Nike\"\"\","));
+
+    let records = golden::read_corpus(corpus.as_bytes())?;
+    let read: Vec<&str> = records.iter().map(|r| r.word.as_str()).collect();
+    assert_eq!(read, words);
+    assert!(encoder.check(&records).is_empty());
+
+    let unclosed = "Aachen,AKN,AXN,AKAN,AXAN,AKN,AXN,AKAN,AXAN\n\n\"Nike,NK,,NK,,NK,,NK,\n";
+    assert!(matches!(
+        golden::read_corpus(unclosed.as_bytes()),
+        Err(golden::GoldenError::Malformed { line: 3 })
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_golden_check_reports_changes() {
+    let mut encoder = GoldenEncoder::new();
+    let mut record = encoder.record("Smith");
+    assert_eq!(
+        record.to_string(),
+        "Smith,SM0,XMT,SMA0,XMAT,SM0,XMT,SMA0,XMAT"
+    );
+
+    record.keys[0].0 = "SMT".into();
+    let diff = encoder.check(&[record]);
+    assert_eq!(diff.changes.len(), 1);
+    assert_eq!(diff.changed(Mode::Enc), 1);
+    assert_eq!(diff.changed(Mode::EncV), 0);
+    assert_eq!(
+        diff.changes[0].to_string(),
+        "Smith [Enc]: SMT/XMT -> SM0/XMT"
+    );
+}
+
+/// Words made of ASCII letters plus the separators that show up in names.
+const NAME_PATTERN: &str = "[A-Za-z][A-Za-z' -]{0,15}";
+
+fn all_modes(word: &str) -> [(crate::String, crate::String); 4] {
+    Mode::ALL.map(|mode| Metaphone3::new().with_mode(mode).encode(word))
+}
+
+proptest! {
+    #[test]
+    fn prop_case_insensitive(word in NAME_PATTERN) {
+        let expected = all_modes(&word.to_uppercase());
+        prop_assert_eq!(all_modes(&word.to_lowercase()), expected.clone());
+        prop_assert_eq!(all_modes(&word), expected);
+    }
+
+    #[test]
+    fn prop_reuse_is_deterministic(
+        previous in prop::collection::vec(any::<std::string::String>(), 0..4),
+        word in any::<std::string::String>(),
+    ) {
+        for mode in Mode::ALL {
+            let fresh = Metaphone3::new().with_mode(mode).encode(&word);
+            let mut reused = Metaphone3::new().with_mode(mode);
+            for p in &previous {
+                reused.encode(p);
+            }
+            prop_assert_eq!(reused.encode(&word), fresh.clone());
+            prop_assert_eq!(reused.encode(&word), fresh);
+        }
+    }
+
+    #[test]
+    fn prop_configuration_is_idempotent(word in NAME_PATTERN, vowels: bool, exact: bool) {
+        let once = Metaphone3::new()
+            .with_encode_vowels(vowels)
+            .with_encode_exact(exact)
+            .encode(&word);
+        let twice = Metaphone3::new()
+            .with_encode_exact(exact)
+            .with_encode_vowels(vowels)
+            .with_encode_exact(exact)
+            .with_encode_vowels(vowels)
+            .encode(&word);
+        let mode = Mode::from_options(vowels, exact);
+        prop_assert_eq!(&once, &twice);
+        prop_assert_eq!(Metaphone3::new().with_mode(mode).mode(), mode);
+        prop_assert_eq!(Mode::ALL[mode.index()], mode);
+        prop_assert_eq!(Metaphone3::new().with_mode(mode).encode(&word), once);
+    }
+
+    #[test]
+    fn prop_latin1_matches_utf8(word in prop::collection::vec(any::<u8>(), 0..16)) {
+        let text: std::string::String = word.iter().copied().map(char::from).collect();
+        for mode in Mode::ALL {
+            let mut encoder = Metaphone3::new().with_mode(mode);
+            prop_assert_eq!(encoder.encode_latin1(&word), encoder.encode(&text));
+            prop_assert_eq!(encoder.encode_chars(text.chars()), encoder.encode(&text));
+        }
+    }
 
+    #[test]
+    fn prop_keys_are_bounded(word in any::<std::string::String>()) {
+        for (primary, secondary) in all_modes(&word) {
+            prop_assert!(primary.chars().count() <= crate::METAPH_MAX_LENGTH);
+            prop_assert!(secondary.chars().count() <= crate::METAPH_MAX_LENGTH);
+            prop_assert!(secondary.is_empty() || secondary != primary);
+        }
+    }
+
+    #[test]
+    fn prop_packed_key_order(a in "[0A-Z]{0,8}", b in "[0-9A-Z]{0,8}") {
+        let (pa, pb) = (PackedKey::new(&a).unwrap(), PackedKey::new(&b).unwrap());
+        prop_assert_eq!(pa.to_string(), a.clone());
+        prop_assert_eq!(PackedKey::from_u64(pb.to_u64()), Some(pb));
+        prop_assert_eq!(pa.len(), a.len());
+        prop_assert_eq!(pa.cmp(&pb), a.cmp(&b));
+    }
+
+    /// Exact mode only swaps approximate consonants for exact ones, so it never
+    /// changes key lengths; vowel mode only adds vowels, so it never shortens.
+    #[test]
+    fn prop_modes_refine(word in NAME_PATTERN) {
+        let [enc, enc_ev, enc_e, enc_v] = all_modes(&word);
+        prop_assert_eq!(enc_e.0.len(), enc.0.len());
+        prop_assert_eq!(enc_ev.0.len(), enc_v.0.len());
+        if !enc.1.is_empty() {
+            prop_assert_eq!(enc_e.1.len(), enc.1.len());
+        }
+        prop_assert!(enc_v.0.len() >= enc.0.len());
+        prop_assert!(enc_ev.0.len() >= enc_e.0.len());
+    }
+
+    #[test]
+    fn prop_all_modes_match_encode(word in any::<std::string::String>(), name in NAME_PATTERN) {
+        let mut encoder = Metaphone3::new().with_encode_exact(true);
+        for word in [&word, &name] {
+            prop_assert_eq!(encoder.encode_all_modes(word), all_modes(word));
+        }
+        prop_assert_eq!(encoder.mode(), Mode::EncE);
+    }
+
+    #[test]
+    fn prop_detailed_matches_encode(word in any::<std::string::String>()) {
+        for mode in Mode::ALL {
+            let mut encoder = Metaphone3::new().with_mode(mode);
+            let detailed = encoder.encode_detailed(&word);
+            let keys = encoder.encode(&word);
+            prop_assert_eq!((&detailed.primary, &detailed.secondary), (&keys.0, &keys.1));
+            prop_assert!(detailed.full_primary.starts_with(detailed.primary.as_str()));
+            prop_assert!(detailed.full_secondary.starts_with(detailed.secondary.as_str())
+                || detailed.secondary.is_empty());
+        }
+    }
+
+    #[test]
+    fn prop_try_encode_agrees(word in any::<std::string::String>()) {
         let mut encoder = Metaphone3::new();
-        let mut encoder_v = Metaphone3::new().with_encode_vowels(true);
-        let mut encoder_e = Metaphone3::new().with_encode_exact(true);
-        let mut encoder_ev = Metaphone3::new()
-            .with_encode_vowels(true)
-            .with_encode_exact(true);
-
-        let mut cnt = 0;
-        let mut enc_err = 0;
-        let mut enc_v_err = 0;
-        let mut enc_e_err = 0;
-        let mut enc_ev_err = 0;
-
-        for result in csv_reader.records() {
-            let record = result?;
-            if record.len() < 9 {
-                continue; // skip malformed lines
+        let keys = encoder.encode(&word);
+        match encoder.try_encode(&word) {
+            Ok(checked) => prop_assert_eq!(checked, keys),
+            Err(EncodeError::ControlCharacter { character, .. }) => {
+                prop_assert!(character.is_control() && word.contains(character));
             }
+            Err(_) => prop_assert_eq!(keys, Default::default()),
+        }
+    }
 
-            let input = &record[0];
-            let main_ve = &record[1]; // !v!e
-            let alt_ve = &record[2];
-            let main_v_ev = &record[3]; // v+e
-            let alt_v_ev = &record[4];
-            let main_e = &record[5]; // !v+e
-            let alt_e = &record[6];
-            let main_v = &record[7]; // v!e
-            let alt_v = &record[8];
-
-            cnt += 1;
-
-            check_encoding("Enc", &mut encoder, input, main_ve, alt_ve, &mut enc_err);
-            check_encoding(
-                "EncEV",
-                &mut encoder_ev,
-                input,
-                main_v_ev,
-                alt_v_ev,
-                &mut enc_ev_err,
-            );
-            check_encoding("EncE", &mut encoder_e, input, main_e, alt_e, &mut enc_e_err);
-            check_encoding("EncV", &mut encoder_v, input, main_v, alt_v, &mut enc_v_err);
+    #[test]
+    fn prop_alignment_covers_keys(word in any::<std::string::String>()) {
+        let chars = word.chars().count();
+        for mode in Mode::ALL {
+            let mut encoder = Metaphone3::new().with_mode(mode);
+            let aligned = encoder.encode_aligned(&word);
+            let (primary, secondary) = encoder.encode(&word);
+            prop_assert_eq!(aligned.primary_spans().len(), primary.chars().count());
+            prop_assert_eq!(aligned.secondary_spans().len(), secondary.chars().count());
+            prop_assert_eq!((&aligned.primary, &aligned.secondary), (&primary, &secondary));
+            for span in aligned.primary_spans().iter().chain(aligned.secondary_spans()) {
+                prop_assert!(span.start < span.end && span.end <= chars);
+            }
         }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn prop_cache_matches_encode(words in prop::collection::vec("[a-e]{0,3}", 0..64), capacity in 0usize..8) {
+        let mut cached = CachedMetaphone3::new(Metaphone3::new(), capacity);
+        let mut encoder = Metaphone3::new();
+        for word in &words {
+            prop_assert_eq!(cached.encode(word), encoder.encode(word));
+            prop_assert!(cached.len() <= capacity);
+        }
+        let stats = cached.stats();
+        prop_assert_eq!(stats.hits + stats.misses, words.len() as u64);
+        if capacity > 0 {
+            prop_assert_eq!(stats.evictions, stats.misses - cached.len() as u64);
+        }
+    }
+}
+
+#[test]
+fn test_alignment() {
+    let mut encoder = Metaphone3::new();
+    let aligned = encoder.encode_aligned("Schmidt");
+    assert_eq!(aligned.primary, "XMT");
+    assert_eq!(aligned.primary_spans(), [0..3, 3..4, 5..7]);
+    assert!(aligned.secondary_spans().is_empty());
+    assert_eq!(aligned.silent().collect::<Vec<_>>(), [4]);
+
+    let aligned = encoder.encode_aligned("Knight");
+    assert_eq!(aligned.primary, "NT");
+    assert_eq!(aligned.silent().collect::<Vec<_>>(), [0, 2, 3, 4]);
+
+    // "ß" uppercases to "SS", which still maps back to one input character.
+    let aligned = encoder.encode_aligned("Strauß");
+    assert_eq!(aligned.primary, "STRS");
+    assert_eq!(aligned.primary_spans()[3], 5..6);
+
+    let mut encoder = Metaphone3::new().with_encode_vowels(true);
+    let aligned = encoder.encode_aligned("Anna");
+    assert_eq!(aligned.primary, "ANA");
+    assert_eq!(aligned.primary_spans(), [0..1, 1..3, 3..4]);
+    assert!(!aligned.is_silent(2) && !aligned.is_silent(10));
+
+    let exceptions = ExceptionDictionary::parse("Mc-,MK,\nNike,NK,\n").unwrap();
+    let mut encoder = Metaphone3::new().with_exceptions(exceptions);
+    let aligned = encoder.encode_aligned("McArdle");
+    assert_eq!(aligned.primary, "MKRTL");
+    assert_eq!(&aligned.primary_spans()[..2], [0..2, 0..2]);
+    assert_eq!(encoder.encode_aligned("Nike").primary_spans(), [0..4, 0..4]);
+    assert!(encoder.encode_aligned("").primary_spans().is_empty());
+}
+
+#[test]
+fn test_detailed() {
+    let mut encoder = Metaphone3::new();
+    let cases = [
+        ("Wolfgang", Heuristics::SLAVO_GERMANIC),
+        ("Bach", Heuristics::GERMANIC),
+        ("Johann", Heuristics::GERMANIC | Heuristics::SLAVO_GERMANIC),
+        ("Michelangelo", Heuristics::GREEK),
+        ("Jorge", Heuristics::SPANISH | Heuristics::SLAVO_GERMANIC),
+        ("Renault", Heuristics::FRENCH),
+        // No rule consults the heuristic for these, but they look it.
+        ("Schwarzenegger", Heuristics::SLAVO_GERMANIC),
+        ("Jankowski", Heuristics::SLAVO_GERMANIC),
+        ("Smith", Heuristics::NONE),
+        ("", Heuristics::NONE),
+    ];
+    for (word, heuristics) in cases {
+        let detailed = encoder.encode_detailed(word);
+        assert_eq!(detailed.heuristics, heuristics, "{word}");
+        assert_eq!(
+            (detailed.primary.clone(), detailed.secondary.clone()),
+            encoder.encode(word)
+        );
+        assert!(!detailed.is_truncated());
+        assert_eq!(detailed.full_primary, detailed.primary);
+    }
+
+    let mut encoder = Metaphone3::new().with_encode_vowels(true);
+    let detailed = encoder.encode_detailed("Battle");
+    assert_eq!(detailed.primary, "PATAL");
+    assert!(detailed.heuristics.contains(Heuristics::AL_INVERSION));
+    assert_eq!(
+        format!("{:?}", Heuristics::GREEK | Heuristics::FRENCH),
+        "Heuristics(GREEK | FRENCH)"
+    );
+
+    // Heuristics of letters past the cut are reported too.
+    let mut encoder = Metaphone3::new();
+    let detailed = encoder.encode_detailed("Constantinopolitanbach");
+    assert!(detailed.primary_truncated());
+    assert_eq!(detailed.heuristics, Heuristics::GERMANIC);
+    assert!(
+        encoder
+            .encode_detailed("Constantinopolitan")
+            .heuristics
+            .is_empty()
+    );
+
+    let detailed = encoder.encode_detailed("Schwarzkopfsteinberger");
+    assert!(detailed.primary_truncated() && detailed.secondary_truncated());
+    assert_eq!(detailed.primary.len(), crate::METAPH_MAX_LENGTH);
+    assert!(detailed.full_primary.starts_with(detailed.primary.as_str()));
+    assert!(
+        detailed
+            .full_secondary
+            .starts_with(detailed.secondary.as_str())
+    );
+    assert!(detailed.has_alternate());
+    // Encoding past the cut must not change the keys.
+    assert_eq!(
+        (detailed.primary, detailed.secondary),
+        encoder.encode("Schwarzkopfsteinberger")
+    );
+}
+
+#[test]
+fn test_try_encode() {
+    let mut encoder = Metaphone3::new();
+    assert_eq!(encoder.try_encode("Smith"), Ok(encoder.encode("Smith")));
+    assert_eq!(encoder.try_encode("Ümit"), Ok(encoder.encode("Ümit")));
+    assert_eq!(encoder.try_encode(""), Err(EncodeError::Empty));
+    for garbage in ["12345", "--", " ", "😀😀", "北京", "#1!"] {
+        assert_eq!(
+            encoder.try_encode(garbage),
+            Err(EncodeError::NoEncodableLetters),
+            "{garbage:?}"
+        );
+    }
+    assert_eq!(
+        encoder.try_encode("Jo\nhn"),
+        Err(EncodeError::ControlCharacter {
+            index: 2,
+            character: '\n'
+        })
+    );
+    // No limit by default.
+    assert!(encoder.try_encode(&"Smith".repeat(1000)).is_ok());
 
-        let print_stat = |name: &str, err: i32, total: i32| {
-            let percent = (f64::from(err) / f64::from(total)) * 100.0;
-            println!("Encoder {name}, error percent: {percent:.2}%");
-        };
+    let mut encoder = Metaphone3::new().with_max_input_length(5);
+    assert!(encoder.try_encode("Smith").is_ok());
+    assert!(encoder.try_encode("Müller").is_err());
+    assert_eq!(
+        encoder.try_encode("Smithe\t"),
+        Err(EncodeError::TooLong { length: 7, max: 5 })
+    );
+    assert_eq!(
+        EncodeError::TooLong { length: 7, max: 5 }.to_string(),
+        "input is 7 characters long, the maximum is 5"
+    );
+    // `encode` ignores the limit.
+    assert_eq!(encoder.encode("Smithe").0, "SM0");
 
-        print_stat("Enc", enc_err, cnt);
-        print_stat("EncEV", enc_ev_err, cnt);
-        print_stat("EncE", enc_e_err, cnt);
-        print_stat("EncV", enc_v_err, cnt);
+    let config = Metaphone3Config::from(&encoder);
+    assert_eq!(config.try_encode("Smith"), encoder.try_encode("Smith"));
+    assert_eq!(
+        config.try_encode("Smithe"),
+        Err(EncodeError::TooLong { length: 6, max: 5 })
+    );
+    assert_eq!(
+        Metaphone3Config::new().try_encode("?"),
+        Err(EncodeError::NoEncodableLetters)
+    );
+}
+
+#[test]
+fn test_exceptions() {
+    let text = "\
+# brand names
+Nike,NK,NKA
+Xfinity,EKSFNT,,EKSFANAT,,EKSFNT,,EKSFANAT,
+Mc-,MK,
+-wicz,FX,
+";
+    let exceptions = Arc::new(ExceptionDictionary::parse(text).unwrap());
+    assert_eq!(exceptions.len(), 4);
+
+    let mut encoder = Metaphone3::new().with_exceptions(Arc::clone(&exceptions));
+    assert_eq!(encoder.encode("NIKE"), ("NK".into(), "NKA".into()));
+    assert_eq!(encoder.encode("xfinity"), ("EKSFNT".into(), "".into()));
+    assert_eq!(encoder.encode("McArdle"), ("MKRTL".into(), "".into()));
+    assert_eq!(encoder.encode("Markiewicz"), ("MRKFX".into(), "".into()));
+    // Patterns must not swallow the whole word.
+    assert_eq!(
+        encoder.encode("Mc"),
+        Metaphone3::new().encode("Mc"),
+        "prefix covering the whole word"
+    );
+
+    let mut encoder_v = Metaphone3::new()
+        .with_encode_vowels(true)
+        .with_exceptions(exceptions);
+    assert_eq!(encoder_v.encode("Xfinity"), ("EKSFANAT".into(), "".into()));
+    for word in ["Xfinity", "McArdle", "Markiewicz", "Smith"] {
+        let per_mode = Mode::ALL.map(|mode| encoder_v.clone().with_mode(mode).encode(word));
+        assert_eq!(encoder_v.encode_all_modes(word), per_mode, "{word}");
+    }
+    assert_eq!(encoder_v.mode(), Mode::EncV);
+}
 
+#[test]
+fn test_exceptions_per_mode() {
+    let mut exceptions = ExceptionDictionary::new();
+    exceptions
+        .insert_mode("Smith", Mode::EncE, "SMT", "")
+        .unwrap();
+    let exceptions = Arc::new(exceptions);
+
+    let mut encoder = Metaphone3::new().with_exceptions(Arc::clone(&exceptions));
+    assert_eq!(encoder.encode("Smith"), ("SM0".into(), "XMT".into()));
+    let mut encoder_e = Metaphone3::new()
+        .with_encode_exact(true)
+        .with_exceptions(exceptions);
+    assert_eq!(encoder_e.encode("Smith"), ("SMT".into(), "".into()));
+
+    let malformed = |text, reason| {
+        let err = ExceptionDictionary::parse(text).unwrap_err();
         assert!(
-            enc_err + enc_ev_err + enc_e_err + enc_v_err == 0,
-            "Errors when processing {path:?}: Enc={enc_err} EncEV={enc_ev_err} EncE={enc_e_err} EncV={enc_v_err}"
+            matches!(&err, ExceptionError::Malformed { line: 2, reason: r } if *r == reason),
+            "{err}"
+        );
+        err.to_string()
+    };
+    let message = malformed("# ok\nNike,NK", MalformedReason::Columns(2));
+    assert_eq!(message, "line 2: expected 3 or 9 columns, found 2");
+    malformed(
+        "\n-Nike-,NK,",
+        MalformedReason::InvalidPattern("-Nike-".into()),
+    );
+    let message = malformed("\nNike,NK,nk", MalformedReason::InvalidKey("nk".into()));
+    assert_eq!(message, "line 2: invalid key \"nk\"");
+    assert!(ExceptionDictionary::new().insert("-x-", "K", "").is_err());
+    assert!(ExceptionDictionary::new().insert("-", "K", "").is_err());
+    assert!(ExceptionDictionary::new().insert("Nike", "nk", "").is_err());
+}
+
+#[test]
+fn test_custom_rules() {
+    let rules = RuleSet::new()
+        // Scandinavian "ø" is a vowel the built-in rules ignore.
+        .after('ø', |ctx: &mut RuleContext<'_>| {
+            if ctx.idx() == 0 || ctx.encode_vowels() {
+                ctx.add('A').unwrap();
+            }
+            true
+        })
+        // A rule that declines must leave no trace.
+        .before('K', |ctx: &mut RuleContext<'_>| {
+            ctx.add_str("XX", "YY").unwrap();
+            ctx.advance(3);
+            false
+        })
+        // "KJ" as in Norwegian "Kjell" is pronounced like "SH"/"CH".
+        .before('K', |ctx: &mut RuleContext<'_>| {
+            if ctx.string_at(0, &["KJ"]) {
+                ctx.add_alt('X', 'K').unwrap();
+                ctx.advance(1);
+                return true;
+            }
+            false
+        });
+    assert_eq!(rules.len(), 3);
+    let rules = Arc::new(rules);
+
+    let mut encoder = Metaphone3::new().with_rules(Arc::clone(&rules));
+    assert_eq!(encoder.encode("Øster"), ("ASTR".into(), "".into()));
+    assert_eq!(encoder.encode("Kjell"), ("XL".into(), "KL".into()));
+    assert_eq!(encoder.encode("Kelly"), Metaphone3::new().encode("Kelly"));
+
+    let mut encoder_v = Metaphone3::new().with_encode_vowels(true).with_rules(rules);
+    assert_eq!(encoder_v.encode("Bjørn"), ("PJARN".into(), "".into()));
+    let per_mode = Mode::ALL.map(|mode| encoder_v.clone().with_mode(mode).encode("Bjørn"));
+    assert_eq!(encoder_v.encode_all_modes("Bjørn"), per_mode);
+}
+
+#[test]
+fn test_rules_reject_unpackable_output() {
+    let invalid = |c| Err(PackedKeyError::InvalidChar(c));
+    let rules = RuleSet::new()
+        .before('B', move |ctx: &mut RuleContext<'_>| {
+            assert_eq!(ctx.add_str("P-1", "P"), invalid('-'));
+            assert_eq!(ctx.add_str("P", "p"), invalid('p'));
+            assert_eq!(ctx.add_str("\0", "P"), invalid('\0'));
+            assert_eq!(ctx.add_alt('ø', 'B'), invalid('ø'));
+            assert_eq!(ctx.add_exact_approx('F', '!'), invalid('!'));
+            assert_eq!(ctx.add('x'), invalid('x'));
+            ctx.add_alt('P', '\0').unwrap();
+            ctx.add_exact_approx('F', 'P').unwrap();
+            ctx.advance(usize::MAX);
+            true
+        })
+        .after('Ø', |ctx: &mut RuleContext<'_>| ctx.add('ß').is_ok());
+    let mut encoder = Metaphone3::new().with_rules(rules);
+    assert_eq!(encoder.encode("Bob"), ("PP".into(), "P".into()));
+    assert_eq!(encoder.encode("Øst"), ("ST".into(), "".into()));
+    let (primary, secondary) = encoder.encode_packed("Bob");
+    assert_eq!(
+        (primary.to_string(), secondary.to_string()),
+        ("PP".into(), "P".into())
+    );
+}
+
+#[test]
+fn test_rules_stay_in_region() {
+    // The rule sees the letter it advanced to; "SON" belongs to the suffix.
+    let rules = RuleSet::new().before('B', |ctx: &mut RuleContext<'_>| {
+        ctx.advance(usize::MAX);
+        ctx.add(ctx.letter(0).unwrap()).is_ok()
+    });
+    let exceptions = ExceptionDictionary::parse("-son,SN,").unwrap();
+    let mut encoder = Metaphone3::new()
+        .with_rules(rules)
+        .with_exceptions(exceptions);
+    assert_eq!(encoder.encode("Bobson"), ("BSN".into(), "".into()));
+    assert_eq!(encoder.encode("Bob"), ("B".into(), "".into()));
+}
+
+#[test]
+fn test_rules_roll_back_exact_output() {
+    let declining = RuleSet::new().before('K', |ctx: &mut RuleContext<'_>| {
+        ctx.add_exact_approx('K', 'X').unwrap();
+        ctx.add_alt('X', 'Y').unwrap();
+        false
+    });
+    let exact_output = |mut encoder: Metaphone3| {
+        encoder.exact_bufs = Some(Default::default());
+        encoder.side_output = true;
+        let keys = encoder.encode("Kelly");
+        (keys, encoder.exact_bufs.take())
+    };
+    assert_eq!(
+        exact_output(Metaphone3::new().with_rules(declining)),
+        exact_output(Metaphone3::new())
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_cache() {
+    let mut cached = CachedMetaphone3::new(Metaphone3::new(), 2);
+    assert_eq!(cached.encode("Smith"), ("SM0".into(), "XMT".into()));
+    assert_eq!(cached.encode("Jones"), ("JNS".into(), "ANS".into()));
+    // Smith is now the most recently used, so Jones is evicted.
+    assert_eq!(cached.encode("Smith"), ("SM0".into(), "XMT".into()));
+    cached.encode("Schmidt");
+    assert_eq!(cached.len(), 2);
+    let stats = CacheStats {
+        hits: 1,
+        misses: 3,
+        evictions: 1,
+    };
+    assert_eq!(cached.stats(), stats);
+    cached.encode("Smith");
+    cached.encode("Jones");
+    assert_eq!(cached.stats().hits, 2);
+    assert_eq!(cached.stats().evictions, 2);
+
+    // Keys of one configuration are not served for another, but are kept.
+    cached.reset_stats();
+    cached.set_encoder(Metaphone3::new().with_mode(Mode::EncV));
+    assert_eq!(cached.encode("Jones"), ("JANS".into(), "ANS".into()));
+    cached.set_encoder(Metaphone3::new());
+    assert_eq!(cached.encode("Jones"), ("JNS".into(), "ANS".into()));
+    assert_eq!((cached.stats().hits, cached.stats().misses), (1, 1));
+    assert!((cached.stats().hit_rate() - 0.5).abs() < f64::EPSILON);
+
+    cached.clear();
+    assert!(cached.is_empty());
+    cached.encode("Jones");
+    assert_eq!(cached.stats().misses, 2);
+
+    // A capacity of 0 caches nothing.
+    let mut uncached = CachedMetaphone3::new(Metaphone3::new(), 0);
+    uncached.encode("Smith");
+    uncached.encode("Smith");
+    assert!(uncached.is_empty());
+    assert_eq!(uncached.stats().hits, 0);
+}
+
+#[test]
+#[cfg(feature = "arrow")]
+fn test_arrow() {
+    use arrow_array::{Array, Int32Array, LargeStringArray, StringArray, StringViewArray};
+
+    let names = [
+        Some("Smith"),
+        None,
+        Some(""),
+        Some("Schmidt"),
+        Some("Jones"),
+    ];
+    let mut encoder = Metaphone3::new();
+    let (primary, secondary) = encoder.encode_string_array(&StringArray::from(names.to_vec()));
+    assert_eq!(primary.len(), names.len());
+    for (i, name) in names.iter().enumerate() {
+        match name {
+            Some(name) => {
+                let (p, s) = encoder.encode(name);
+                assert_eq!(
+                    (primary.value(i), secondary.value(i)),
+                    (p.as_str(), s.as_str())
+                );
+            }
+            None => assert!(primary.is_null(i) && secondary.is_null(i)),
+        }
+    }
+    assert_eq!(primary.null_count(), 1);
+
+    let large = encoder.encode_string_array(&LargeStringArray::from(names.to_vec()));
+    assert_eq!(large, (primary.clone(), secondary.clone()));
+    let view: &dyn Array = &StringViewArray::from(names.to_vec());
+    assert_eq!(encoder.encode_array(view).unwrap(), (primary, secondary));
+    assert!(encoder.encode_array(&Int32Array::from(vec![1])).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_cluster() {
+    let names = [
+        "Smith", "Schmidt", "Smyth", "Smith", "Jon", "John", "Jones", "", "Aachen",
+    ];
+
+    let clusters = Clusterer::new(Metaphone3::new()).cluster(names);
+    let summary: Vec<_> = clusters
+        .iter()
+        .map(|c| (c.key.as_str(), c.rows, c.canonical(), c.values.len()))
+        .collect();
+    assert_eq!(summary, [("SM0", 3, "Smith", 2), ("JN", 2, "Jon", 2)]);
+    assert_eq!(clusters[0].values[1].value, "Smyth");
+    assert_eq!(clusters[0].values[1].count, 1);
+
+    assert!(clusters.iter().all(|c| c.candidates.is_empty()));
+
+    // Smith's secondary key XMT is Schmidt's primary key, which makes
+    // Schmidt a candidate of the Smith cluster without merging the two.
+    let clusters = Clusterer::new(Metaphone3::new())
+        .with_secondary_keys(true)
+        .cluster(names);
+    assert_eq!(clusters[0].rows, 3);
+    assert_eq!(clusters[0].values.len(), 2);
+    let candidates: Vec<_> = clusters[0].candidates.iter().map(|v| &v.value).collect();
+    assert_eq!(candidates, ["Schmidt"]);
+
+    // Secondary keys never chain clusters together: Smith (SM0/XMT) links the
+    // SM0 and XMT clusters only as candidates of each other.
+    let chained = ["Smith", "Smyth", "Schmidt", "Schmitt", "Schmidt"];
+    let clusters = Clusterer::new(Metaphone3::new())
+        .with_secondary_keys(true)
+        .cluster(chained);
+    let summary: Vec<_> = clusters
+        .iter()
+        .map(|c| {
+            let values: Vec<_> = c.values.iter().map(|v| v.value.as_str()).collect();
+            let candidates: Vec<_> = c.candidates.iter().map(|v| v.value.as_str()).collect();
+            (c.key.as_str(), values, candidates)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("XMT", vec!["Schmidt", "Schmitt"], vec!["Smith", "Smyth"]),
+            ("SM0", vec!["Smith", "Smyth"], vec!["Schmidt", "Schmitt"]),
+        ]
+    );
+
+    // Singletons are reported on request, but never values without keys.
+    let clusters = Clusterer::new(Metaphone3::new())
+        .with_min_values(1)
+        .cluster(names);
+    assert_eq!(clusters.len(), 5);
+    assert!(clusters.iter().all(|c| !c.key.is_empty()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_blocking_keys() {
+    let mut strategy = BlockingStrategy::new(Metaphone3::new())
+        .keys("first")
+        .keys("last")
+        .exact("city")
+        .with_separator(':');
+
+    let mut record = HashMap::new();
+    record.insert("first", "Aachen");
+    record.insert("last", "Smith");
+    record.insert("city", " Boston ");
+    assert_eq!(
+        strategy.block_keys(&record),
+        [
+            "AKN:SM0:BOSTON",
+            "AKN:XMT:BOSTON",
+            "AXN:SM0:BOSTON",
+            "AXN:XMT:BOSTON"
+        ]
+    );
+
+    // No keys when a part is empty.
+    record.insert("city", "");
+    assert!(strategy.block_keys(&record).is_empty());
+    record.remove("city");
+    assert!(strategy.block_keys(&record).is_empty());
+
+    let mut strategy = BlockingStrategy::new(Metaphone3::new())
+        .primary("last")
+        .prefix("first", 2);
+    let record: BTreeMap<String, String> = [("first", "ölaf"), ("last", "Schmidt")]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+    assert_eq!(strategy.block_keys(&record), ["XMT|ÖL"]);
+    assert!(
+        strategy
+            .block_keys(&[("last", "123"), ("first", "Al")][..])
+            .is_empty()
+    );
+
+    // A strategy without parts does not put every record in one block.
+    let mut strategy = BlockingStrategy::new(Metaphone3::new());
+    assert!(strategy.block_keys(&record).is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[should_panic(expected = "blocking prefix length must be at least 1")]
+fn test_blocking_empty_prefix() {
+    let _ = BlockingStrategy::new(Metaphone3::new()).prefix("first", 0);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_candidate_pairs() {
+    let left = ["Smith", "Schmidt", "Aachen", "Jon", "John", "123", "Smith"];
+    let right = vec![
+        "Smyth".to_owned(),
+        "Acken".to_owned(),
+        String::new(),
+        "Johnny".to_owned(),
+    ];
+    let index = PhoneticIndex::new(Metaphone3::new(), &left);
+    assert_eq!(index.len(), 7);
+
+    let pairs: Vec<_> = index
+        .candidates(&right)
+        .map(|p| (p.left, p.right, p.class, p.score))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            (0, 0, MatchClass::Primary, None),
+            (6, 0, MatchClass::Primary, None),
+            (1, 0, MatchClass::Cross, None),
+            (2, 1, MatchClass::Primary, None),
+            (3, 3, MatchClass::Primary, None),
+            (4, 3, MatchClass::Primary, None),
+        ]
+    );
+
+    // The "XMT" block has three names and is skipped, so Schmidt is dropped.
+    let index = index.with_max_block(2).with_scoring(true);
+    let pairs: Vec<_> = index.candidates(["Smyth"]).collect();
+    assert_eq!(pairs.len(), 2);
+    assert_eq!((pairs[0].left, pairs[1].left), (0, 6));
+    assert!(pairs[0].score.unwrap() > 0.8);
+
+    // A pair found through its secondary key is not classified by a primary
+    // key over the limit.
+    let exceptions = ExceptionDictionary::parse("Alpha,KK,SS\nBeta,KK,\nGamma,KK,").unwrap();
+    let encoder = Metaphone3::new().with_exceptions(exceptions);
+    let left = ["Alpha", "Beta", "Gamma"];
+    let index = PhoneticIndex::new(encoder, &left).with_max_block(2);
+    let pairs: Vec<_> = index
+        .candidates(["Alpha"])
+        .map(|p| (p.left, p.class))
+        .collect();
+    assert_eq!(pairs, [(0, MatchClass::Secondary)]);
+
+    let key = |s: &str| PackedKey::new(s).unwrap();
+    let secondary = MatchClass::of((key("A"), key("B")), (key("C"), key("B")));
+    assert_eq!(secondary, Some(MatchClass::Secondary));
+    assert_eq!(
+        MatchClass::of((key("A"), PackedKey::EMPTY), (key("B"), PackedKey::EMPTY)),
+        None
+    );
+
+    assert!((jaro_winkler("MARTHA", "marhta") - 0.961).abs() < 0.001);
+    assert!((jaro_winkler("DIXON", "DICKSONX") - 0.813).abs() < 0.001);
+    assert!((jaro_winkler("abc", "ABC") - 1.0).abs() < f64::EPSILON);
+    assert!(jaro_winkler("abc", "xyz").abs() < f64::EPSILON);
+    assert!(jaro_winkler("", "abc").abs() < f64::EPSILON);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_suggest() -> Result<(), Box<dyn std::error::Error>> {
+    let list = "phonetic 1000\nfanatic 2000\nphonetics 900\nFonetik 5\nthe 99999\n";
+    let suggester = Suggester::from_frequency_list(Metaphone3::new(), list.as_bytes())?;
+    assert_eq!(suggester.len(), 5);
+
+    // The query itself is skipped whatever its case; fanatic is twice as
+    // frequent as phonetic at the same distance.
+    let found: Vec<_> = suggester
+        .suggest("fonetik", 5)
+        .iter()
+        .map(|s| (s.word, s.count, s.distance, s.class))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("fanatic", 2000, 3, MatchClass::Primary),
+            ("phonetic", 1000, 3, MatchClass::Primary),
+        ]
+    );
+    assert_eq!(suggester.suggest("fonetik", 1).len(), 1);
+    assert!(suggester.suggest("", 5).is_empty());
+
+    // Each edit counts as a word five times rarer: fanatic is 5 edits away
+    // but 400 times as frequent as Fonetik, which is 2 edits away.
+    let found: Vec<_> = suggester
+        .suggest("phonetik", 5)
+        .iter()
+        .map(|s| s.word)
+        .collect();
+    assert_eq!(found, ["phonetic", "fanatic", "Fonetik"]);
+
+    // Suggestions for several queries can be kept at once.
+    let (first, second) = (suggester.suggest("fonetik", 5), suggester.suggest("the", 5));
+    assert_eq!((first.len(), second.len()), (2, 0));
+
+    let suggester = suggester.with_max_distance(1);
+    assert_eq!(suggester.suggest("phonetik", 5)[0].word, "phonetic");
+    assert_eq!(suggester.suggest("phonetik", 5).len(), 1);
+
+    // Without counts the list is ranked by position.
+    let ranked =
+        Suggester::from_frequency_list(Metaphone3::new(), "fanatic\nphonetic\n".as_bytes())?;
+    let counts: Vec<_> = ranked
+        .suggest("fonetik", 5)
+        .iter()
+        .map(|s| s.count)
+        .collect();
+    assert_eq!(counts, [2, 1]);
+
+    // Invalid counts, and lists where only some words have counts.
+    for list in ["the 1\nof many\n", "the 1\nof\n", "the\n\nof 1\n"] {
+        let error = Suggester::from_frequency_list(Metaphone3::new(), list.as_bytes());
+        assert_eq!(
+            error.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData),
+            "{list:?}"
         );
     }
 
+    // Words and queries are encoded with the encoder's configuration.
+    let mut vowels = Suggester::new(Metaphone3Config::new().with_encode_vowels(true));
+    vowels.insert("Jones", 1);
+    vowels.insert("Janis", 1);
+    assert_eq!(vowels.suggest("Jonas", 5)[0].word, "Janis");
+    assert_eq!(vowels.suggest("Jonas", 5).len(), 1);
+
+    assert_eq!(levenshtein("kitten", "SITTING"), 3);
+    assert_eq!(levenshtein("", "abc"), 3);
+    assert_eq!(levenshtein("Straße", "STRASSE"), 0);
+
+    let path = Path::new("testdata/count_1w.txt");
+    if path.exists() {
+        let suggester =
+            Suggester::from_frequency_list(Metaphone3::new(), BufReader::new(File::open(path)?))?;
+        for (query, expected) in [
+            ("recieve", "receive"),
+            ("fysics", "physics"),
+            ("thier", "their"),
+            ("definately", "definitely"),
+        ] {
+            assert_eq!(suggester.suggest(query, 1)[0].word, expected, "{query}");
+        }
+    }
     Ok(())
 }
 
-fn check_encoding(
-    name: &str,
-    encoder: &mut Metaphone3,
-    input: &str,
-    expected_primary: &str,
-    expected_secondary: &str,
-    error_count: &mut i32,
-) {
-    let (primary, secondary) = encoder.encode(input);
-
-    let mut had_error = false;
-    if primary != expected_primary {
-        eprintln!(
-            "Error Encoding '{input}' with {name}: Primary want '{expected_primary}', got '{primary}'"
+#[test]
+#[cfg(feature = "std")]
+fn test_disk_index() {
+    let names = [
+        "Smith", "Schmidt", "Aachen", "Jones", "123", "Smith", "Smyth",
+    ];
+    let mut builder = DiskIndexBuilder::new(Metaphone3::new());
+    for (id, name) in names.iter().enumerate() {
+        builder.insert(id as u64 * 100, name);
+    }
+    builder.insert(0, "Smith"); // duplicates are dropped
+    let bytes = builder.to_bytes();
+
+    let mut encoder = Metaphone3::new();
+    let index = DiskIndex::from_bytes(&bytes, &encoder).unwrap();
+    let keys: Vec<String> = index.keys().map(|k| k.to_string()).collect();
+    assert_eq!(keys, ["AKN", "ANS", "AXN", "JNS", "SM0", "XMT"]);
+    assert_eq!(index.key_count(), 6);
+    assert_eq!(index.id_count(), 11);
+    assert_eq!(
+        index.get("XMT".parse().unwrap()).collect::<Vec<_>>(),
+        [0, 100, 500, 600]
+    );
+    assert_eq!(index.get("SMT".parse().unwrap()).len(), 0);
+    assert_eq!(index.lookup(&mut encoder, "Smithe"), [0, 100, 500, 600]);
+    assert_eq!(index.lookup(&mut encoder, "Akhen"), [200]);
+    assert!(index.lookup(&mut encoder, "").is_empty());
+
+    let vowels = Metaphone3::new().with_encode_vowels(true);
+    assert!(matches!(
+        DiskIndex::from_bytes(&bytes, &vowels),
+        Err(DiskIndexError::Stale)
+    ));
+    assert_eq!(index.fingerprint(), encoder.fingerprint());
+    let exceptions = ExceptionDictionary::parse("Smith,SMT,").unwrap();
+    let custom = Metaphone3::new().with_exceptions(exceptions);
+    assert!(matches!(
+        DiskIndex::from_bytes(&bytes, &custom),
+        Err(DiskIndexError::Stale)
+    ));
+    assert!(matches!(
+        DiskIndex::from_bytes(&bytes[..bytes.len() - 1], &encoder),
+        Err(DiskIndexError::Truncated)
+    ));
+    assert!(matches!(
+        DiskIndex::from_bytes(b"MPH3", &encoder),
+        Err(DiskIndexError::NotAnIndex)
+    ));
+    let mut future = bytes.clone();
+    future[8] = 99;
+    assert!(matches!(
+        DiskIndex::from_bytes(&future, &encoder),
+        Err(DiskIndexError::UnsupportedVersion(99))
+    ));
+
+    // Corrupt offsets give wrong lookups, but no panic.
+    for offset in [u64::MAX / 4, u64::MAX, 5] {
+        let mut corrupt = bytes.clone();
+        corrupt[48..56].copy_from_slice(&offset.to_le_bytes());
+        let index = DiskIndex::from_bytes(&corrupt, &encoder).unwrap();
+        assert!(index.lookup(&mut encoder, "Aachen").len() <= 1);
+        assert_eq!(index.lookup(&mut encoder, "Jones"), [300]);
+    }
+
+    // An empty index is valid.
+    let empty = DiskIndexBuilder::new(Metaphone3::new()).to_bytes();
+    let index = DiskIndex::from_bytes(&empty, &encoder).unwrap();
+    assert!(index.lookup(&mut encoder, "Smith").is_empty());
+
+    let path = std::env::temp_dir().join(format!("metaphone3-{}.idx", std::process::id()));
+    builder.save(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    #[cfg(feature = "mmap")]
+    {
+        let mapped = crate::disk::MappedIndex::open(&path, &encoder).unwrap();
+        assert_eq!(
+            mapped.index().lookup(&mut encoder, "Smyth"),
+            [0, 100, 500, 600]
         );
-        had_error = true;
+        assert!(crate::disk::MappedIndex::open(&path, &vowels).is_err());
+
+        // Saving over a mapped file replaces it; the map keeps the old file.
+        let mut other = DiskIndexBuilder::new(Metaphone3::new());
+        other.insert(7, "Jones");
+        other.save(&path).unwrap();
+        assert_eq!(mapped.index().lookup(&mut encoder, "Jones"), [300]);
+        let reopened = crate::disk::MappedIndex::open(&path, &encoder).unwrap();
+        assert_eq!(reopened.index().lookup(&mut encoder, "Jones"), [7]);
     }
-    if secondary != expected_secondary {
-        eprintln!(
-            "Error Encoding '{input}' with {name}: Secondary want '{expected_secondary}', got '{secondary}'"
+
+    // Threads saving to the same path do not share a temporary file.
+    std::thread::scope(|scope| {
+        for thread in 0..8 {
+            let path = &path;
+            scope.spawn(move || {
+                for id in 0..20 {
+                    let mut builder = DiskIndexBuilder::new(Metaphone3::new());
+                    builder.insert(thread * 100 + id, "Jones");
+                    builder.save(path).unwrap();
+                }
+            });
+        }
+    });
+    let bytes = std::fs::read(&path).unwrap();
+    let index = DiskIndex::from_bytes(&bytes, &encoder).unwrap();
+    assert_eq!(index.lookup(&mut encoder, "Jones").len(), 1);
+    let dir = path.parent().unwrap();
+    let name = path.file_name().unwrap().to_str().unwrap();
+    let leftovers = std::fs::read_dir(dir)
+        .unwrap()
+        .filter(|entry| {
+            let entry = entry.as_ref().unwrap().file_name();
+            entry.to_str().unwrap().starts_with(&format!("{name}."))
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Digests of the golden corpora in `testdata/`, per [`ALGORITHM_VERSION`].
+///
+/// If `test_golden_digest` fails after a rule change, the rules now produce
+/// different keys: bump `ALGORITHM_VERSION` and add a new entry here. Never
+/// edit an existing entry.
+#[cfg(feature = "std")]
+const GOLDEN_DIGESTS: &[(u32, u64)] = &[(1, 0xd91a_658a_0156_93f9)];
+
+#[test]
+#[cfg(feature = "std")]
+fn test_golden_digest() -> Result<(), Box<dyn std::error::Error>> {
+    let testdata_dir = Path::new("testdata");
+    if !testdata_dir.exists() {
+        eprintln!("Skipping test_golden_digest: testdata/ directory not found");
+        return Ok(());
+    }
+
+    let mut paths: Vec<_> = std::fs::read_dir(testdata_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension() == Some("test".as_ref()));
+    paths.sort();
+
+    let mut hash = Fnv1a::new();
+    for path in paths {
+        for record in golden::read_corpus(BufReader::new(File::open(&path)?))? {
+            hash.write_str(&record.to_string());
+        }
+    }
+
+    let mut versions: Vec<u32> = GOLDEN_DIGESTS.iter().map(|&(v, _)| v).collect();
+    versions.sort_unstable();
+    versions.dedup();
+    assert_eq!(
+        versions.len(),
+        GOLDEN_DIGESTS.len(),
+        "versions must be unique"
+    );
+    let pinned = GOLDEN_DIGESTS
+        .iter()
+        .find(|&&(v, _)| v == ALGORITHM_VERSION)
+        .map(|&(_, digest)| digest);
+    assert_eq!(
+        pinned,
+        Some(hash.finish()),
+        "golden outputs changed: bump ALGORITHM_VERSION and record the new digest"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint() {
+    // Fingerprints are stable across releases and platforms.
+    let fingerprints: Vec<String> = Mode::ALL
+        .into_iter()
+        .map(|mode| Metaphone3::new().with_mode(mode).fingerprint().to_string())
+        .collect();
+    assert_eq!(
+        fingerprints,
+        [
+            "3086ab6d5249de6c",
+            "c6150682920a6892",
+            "c3f100cae6e4d47f",
+            "597f5be026a55ea5"
+        ]
+    );
+
+    let fingerprint = Metaphone3::new().fingerprint();
+    assert_eq!(fingerprint.to_string().parse(), Ok(fingerprint));
+    assert_eq!(Fingerprint::from_u64(fingerprint.to_u64()), fingerprint);
+
+    // Non-empty exception dictionaries and rule sets change the fingerprint.
+    let empty = Metaphone3::new()
+        .with_exceptions(ExceptionDictionary::new())
+        .with_rules(RuleSet::new());
+    assert_eq!(empty.fingerprint(), fingerprint);
+    let nike = ExceptionDictionary::parse("Nike,NK,").unwrap();
+    let with_nike = Metaphone3::new().with_exceptions(nike).fingerprint();
+    assert_ne!(with_nike, fingerprint);
+    let nyke = ExceptionDictionary::parse("Nike,NYK,").unwrap();
+    assert_ne!(
+        Metaphone3::new().with_exceptions(nyke).fingerprint(),
+        with_nike
+    );
+    let rules = RuleSet::new().after('X', |_: &mut RuleContext<'_>| false);
+    let with_rules = Metaphone3::new().with_rules(rules.clone()).fingerprint();
+    assert_ne!(with_rules, fingerprint);
+
+    // Rule sets of the same size differ by version and by letter.
+    let versioned = rules.with_version("2");
+    assert_eq!(versioned.version(), "2");
+    let other_letter = RuleSet::new().before('X', |_: &mut RuleContext<'_>| false);
+    for other in [versioned, other_letter] {
+        assert_eq!(other.len(), 1);
+        assert_ne!(
+            Metaphone3::new().with_rules(other).fingerprint(),
+            with_rules
         );
-        had_error = true;
     }
+}
+
+#[test]
+fn test_shared_config() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Metaphone3Config>();
 
-    if had_error {
-        *error_count += 1;
+    let words = [
+        "Smith", "Schmidt", "Aachen", "McArdle", "Nike", "Xiang", "", "ölaf",
+    ];
+    let exceptions = Arc::new(ExceptionDictionary::parse("Nike,NK,NKA\nMc-,MK,").unwrap());
+    let rules = Arc::new(RuleSet::new().before('X', |ctx: &mut RuleContext<'_>| {
+        // Encoding through a shared config from inside a rule must not
+        // conflict with the outer call's scratch encoder.
+        let inner = Metaphone3Config::new().encode("Xavier");
+        ctx.add(inner.0.chars().next().unwrap()).unwrap();
+        true
+    }));
+
+    for mode in Mode::ALL {
+        let config = Metaphone3Config::new()
+            .with_mode(mode)
+            .with_exceptions(Arc::clone(&exceptions))
+            .with_rules(Arc::clone(&rules));
+        let mut encoder = Metaphone3::from(config.clone());
+        assert_eq!(config.mode(), mode);
+        assert_eq!(config.fingerprint(), encoder.fingerprint());
+        assert_eq!(
+            Metaphone3Config::from(&encoder).fingerprint(),
+            config.fingerprint()
+        );
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for word in words {
+                        assert_eq!(config.encode(word), config.encoder().encode(word));
+                    }
+                });
+            }
+        });
+        for word in words {
+            assert_eq!(config.encode(word), encoder.encode(word));
+            assert_eq!(
+                config.encode_latin1(word.as_bytes()),
+                encoder.encode_latin1(word.as_bytes())
+            );
+        }
     }
+    let config = Metaphone3Config::new().with_rules(rules);
+    assert_eq!(config.encode("Xiang").0, "SNK");
 }
@@ -0,0 +1,124 @@
+//! Encoding that rejects input it cannot encode meaningfully.
+//!
+//! [`Metaphone3::encode`] returns empty keys for anything without encodable
+//! letters: the empty string, but also digits, punctuation, emoji or CJK text.
+//! [`Metaphone3::try_encode`] reports those cases as an [`EncodeError`]
+//! instead, so they are not indexed under the empty key by accident. It also
+//! rejects control characters and, if a limit is set with
+//! [`with_max_input_length`](Metaphone3::with_max_input_length), overlong
+//! input.
+//!
+//! ```
+//! use metaphone3::Metaphone3;
+//! use metaphone3::validate::EncodeError;
+//!
+//! let mut encoder = Metaphone3::new().with_max_input_length(32);
+//! assert_eq!(encoder.try_encode("Smith"), Ok(("SM0".into(), "XMT".into())));
+//! assert_eq!(encoder.try_encode(""), Err(EncodeError::Empty));
+//! assert_eq!(encoder.try_encode("1234 ☺"), Err(EncodeError::NoEncodableLetters));
+//! assert_eq!(
+//!     encoder.try_encode("Smith\0"),
+//!     Err(EncodeError::ControlCharacter { index: 5, character: '\0' })
+//! );
+//! ```
+
+use core::fmt;
+
+use crate::{Metaphone3, String};
+
+/// Why [`Metaphone3::try_encode`] rejected a word.
+///
+/// The checks run in the order of the variants, so a word that fails several
+/// of them reports the first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    /// The input is empty.
+    Empty,
+    /// The input is longer than the
+    /// [configured maximum](Metaphone3::with_max_input_length).
+    TooLong {
+        /// Length of the input in characters.
+        length: usize,
+        /// The configured maximum.
+        max: usize,
+    },
+    /// The input contains a control character, such as a NUL, a tab or a line
+    /// break.
+    ControlCharacter {
+        /// Position of the first control character, in characters.
+        index: usize,
+        /// The control character.
+        character: char,
+    },
+    /// The input has no letters the rules encode, only digits, punctuation,
+    /// symbols, letters of other scripts or silent letters, so both keys
+    /// would be empty.
+    NoEncodableLetters,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::Empty => f.write_str("input is empty"),
+            EncodeError::TooLong { length, max } => {
+                write!(f, "input is {length} characters long, the maximum is {max}")
+            }
+            EncodeError::ControlCharacter { index, character } => {
+                write!(f, "control character {character:?} at position {index}")
+            }
+            EncodeError::NoEncodableLetters => f.write_str("input has no encodable letters"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+impl Metaphone3 {
+    /// Sets the maximum length, in characters, of the input
+    /// [`try_encode`](Self::try_encode) accepts. Unlimited by default.
+    ///
+    /// [`encode`](Self::encode) ignores the limit.
+    #[must_use]
+    pub fn with_max_input_length(mut self, max: usize) -> Self {
+        self.max_input_length = max;
+        self
+    }
+
+    /// Encodes a word like [`encode`](Self::encode), but fails instead of
+    /// returning empty keys. See the [`validate`](crate::validate) module.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] for empty input, input over the
+    /// [maximum length](Self::with_max_input_length), input with control
+    /// characters and input without encodable letters.
+    pub fn try_encode(&mut self, word: &str) -> Result<(String, String), EncodeError> {
+        validate(word, self.max_input_length)?;
+        let (primary, secondary) = self.encode(word);
+        if primary.is_empty() && secondary.is_empty() {
+            return Err(EncodeError::NoEncodableLetters);
+        }
+        Ok((primary, secondary))
+    }
+}
+
+/// Runs the checks that do not need the keys.
+fn validate(word: &str, max_length: usize) -> Result<(), EncodeError> {
+    if word.is_empty() {
+        return Err(EncodeError::Empty);
+    }
+    if word.len() > max_length {
+        let length = word.chars().count();
+        if length > max_length {
+            return Err(EncodeError::TooLong {
+                length,
+                max: max_length,
+            });
+        }
+    }
+    match word.chars().enumerate().find(|(_, c)| c.is_control()) {
+        Some((index, character)) => Err(EncodeError::ControlCharacter { index, character }),
+        None => Ok(()),
+    }
+}